@@ -0,0 +1,72 @@
+use nalgebra_glm::Vec3;
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Aabb {
+            min: a.inf(&b),
+            max: a.sup(&b),
+        }
+    }
+
+    pub fn from_point(point: Vec3) -> Self {
+        Aabb::new(point, point)
+    }
+
+    /// Box around a sphere
+    pub fn around(center: Vec3, radius: f32) -> Self {
+        let extent = Vec3::new(radius, radius, radius).abs();
+        Aabb::new(center - extent, center + extent)
+    }
+
+    pub fn min(&self) -> Vec3 {
+        self.min
+    }
+
+    pub fn max(&self) -> Vec3 {
+        self.max
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Index of the longest axis
+    pub fn longest_axis(&self) -> usize {
+        self.diagonal().imax()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union() {
+        let a = Aabb::new(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 1.0));
+        let b = Aabb::from_point(Vec3::new(3.0, -1.0, 0.5));
+        let union = a.union(&b);
+
+        assert_eq!(union.min(), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(union.max(), Vec3::new(3.0, 1.0, 1.0));
+        assert_eq!(union.longest_axis(), 0);
+        assert_eq!(union.center(), Vec3::new(1.5, 0.0, 0.5));
+    }
+}
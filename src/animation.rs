@@ -0,0 +1,248 @@
+use std::ops::{Add, Sub, Mul};
+use nalgebra_glm::{Vec3, Quat};
+
+/// Rigid transform from object space to world space: rotation followed by translation
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+}
+
+/// Keyframed transform, linearly interpolated between keys and held constant outside the key range
+#[derive(Debug, Clone)]
+pub struct TransformAnimation {
+    keyframes: Vec<Keyframe<Transform>>,
+}
+
+/// Camera placement at one point in time, `vertical_fov` in degrees
+#[derive(Debug, Clone, Copy)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub vertical_fov: f32,
+}
+
+/// Keyframed camera path, interpolated with a cubic spline through the keys
+#[derive(Debug, Clone)]
+pub struct CameraAnimation {
+    keyframes: Vec<Keyframe<CameraPose>>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn new(translation: Vec3, rotation: Quat) -> Self {
+        Transform {
+            translation,
+            rotation: nalgebra_glm::quat_normalize(&rotation),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Transform::new(Vec3::zeros(), nalgebra_glm::quat_identity())
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Transform::new(translation, nalgebra_glm::quat_identity())
+    }
+
+    /// Rotation of `angle` degrees around `axis`, followed by a translation
+    pub fn from_axis_angle(translation: Vec3, axis: Vec3, angle: f32) -> Self {
+        Transform::new(translation, nalgebra_glm::quat_angle_axis(f32::to_radians(angle), &axis.normalize()))
+    }
+
+    pub fn transform_point(&self, point: &Vec3) -> Vec3 {
+        self.transform_vector(point) + self.translation
+    }
+
+    pub fn transform_vector(&self, vector: &Vec3) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.rotation, vector)
+    }
+
+    pub fn inverse_transform_point(&self, point: &Vec3) -> Vec3 {
+        self.inverse_transform_vector(&(point - self.translation))
+    }
+
+    pub fn inverse_transform_vector(&self, vector: &Vec3) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&nalgebra_glm::quat_conjugate(&self.rotation), vector)
+    }
+
+    pub fn interpolate(&self, other: &Transform, t: f32) -> Transform {
+        Transform {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: nalgebra_glm::quat_slerp(&self.rotation, &other.rotation, t),
+        }
+    }
+}
+
+impl TransformAnimation {
+    pub fn fixed(transform: Transform) -> Self {
+        TransformAnimation::keyframes(vec![Keyframe { time: 0.0, value: transform }])
+    }
+
+    pub fn linear(start_time: f32, start: Transform, end_time: f32, end: Transform) -> Self {
+        TransformAnimation::keyframes(vec![
+            Keyframe { time: start_time, value: start },
+            Keyframe { time: end_time, value: end },
+        ])
+    }
+
+    pub fn keyframes(mut keyframes: Vec<Keyframe<Transform>>) -> Self {
+        assert!(!keyframes.is_empty(), "Animation needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        TransformAnimation {
+            keyframes,
+        }
+    }
+
+    /// Transforms at each keyframe, in time order
+    pub fn keyframe_transforms(&self) -> impl Iterator<Item = &Transform> + '_ {
+        self.keyframes.iter().map(|keyframe| &keyframe.value)
+    }
+
+    pub fn evaluate(&self, time: f32) -> Transform {
+        let (before, after, t) = keyframe_segment(&self.keyframes, time);
+
+        before.value.interpolate(&after.value, t)
+    }
+}
+
+impl CameraAnimation {
+    pub fn keyframes(mut keyframes: Vec<Keyframe<CameraPose>>) -> Self {
+        assert!(!keyframes.is_empty(), "Animation needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        CameraAnimation {
+            keyframes,
+        }
+    }
+
+    pub fn evaluate(&self, time: f32) -> CameraPose {
+        CameraPose {
+            position: spline_evaluate(&self.keyframes, time, |pose| pose.position),
+            target: spline_evaluate(&self.keyframes, time, |pose| pose.target),
+            vertical_fov: spline_evaluate(&self.keyframes, time, |pose| pose.vertical_fov),
+        }
+    }
+}
+
+/// Cubic Hermite spline through the keyframe values with Catmull-Rom tangents, held constant outside the key range
+pub fn spline_evaluate<T, V, F>(keyframes: &[Keyframe<T>], time: f32, value: F) -> V
+where
+    F: Fn(&T) -> V,
+    V: Copy + Add<Output = V> + Sub<Output = V> + Mul<f32, Output = V>
+{
+    let last = keyframes.len() - 1;
+    if time <= keyframes[0].time {
+        return value(&keyframes[0].value)
+    }
+    if time >= keyframes[last].time {
+        return value(&keyframes[last].value)
+    }
+
+    let index = keyframes.partition_point(|keyframe| keyframe.time <= time) - 1;
+    let segment_duration = keyframes[index + 1].time - keyframes[index].time;
+
+    // Tangents from the neighbouring keys, scaled to the segment so unevenly spaced keys stay smooth
+    let tangent = |i: usize| -> V {
+        let (previous, next) = (i.saturating_sub(1), usize::min(i + 1, last));
+        let duration = keyframes[next].time - keyframes[previous].time;
+        let slope = value(&keyframes[next].value) - value(&keyframes[previous].value);
+
+        if duration <= 0.0 { slope * 0.0 } else { slope * (segment_duration / duration) }
+    };
+
+    let s = (time - keyframes[index].time) / segment_duration;
+    let (s2, s3) = (s * s, s * s * s);
+
+    value(&keyframes[index].value) * (2.0 * s3 - 3.0 * s2 + 1.0)
+        + tangent(index) * (s3 - 2.0 * s2 + s)
+        + value(&keyframes[index + 1].value) * (3.0 * s2 - 2.0 * s3)
+        + tangent(index + 1) * (s3 - s2)
+}
+
+/// Keyframes surrounding `time` and the normalized position between them, keyframes must be sorted by time
+pub fn keyframe_segment<T>(keyframes: &[Keyframe<T>], time: f32) -> (&Keyframe<T>, &Keyframe<T>, f32) {
+    let first = &keyframes[0];
+    let last = &keyframes[keyframes.len() - 1];
+
+    if time <= first.time {
+        return (first, first, 0.0)
+    }
+    if time >= last.time {
+        return (last, last, 0.0)
+    }
+
+    let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+    let (before, after) = (&keyframes[next - 1], &keyframes[next]);
+
+    (before, after, (time - before.time) / (after.time - before.time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_roundtrip() {
+        let transform = Transform::from_axis_angle(Vec3::new(1.0, 2.0, 3.0), Vec3::new(0.0, 1.0, 0.0), 90.0);
+        let point = Vec3::new(1.0, 0.0, 0.0);
+
+        let world = transform.transform_point(&point);
+        assert!((world - Vec3::new(1.0, 2.0, 2.0)).magnitude() < 1e-5);
+        assert!((transform.inverse_transform_point(&world) - point).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_linear_animation() {
+        let animation = TransformAnimation::linear(
+            0.0, Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
+            1.0, Transform::from_translation(Vec3::new(2.0, 0.0, 0.0))
+        );
+
+        assert!((animation.evaluate(0.5).translation - Vec3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((animation.evaluate(-1.0).translation - Vec3::new(0.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((animation.evaluate(2.0).translation - Vec3::new(2.0, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_keyframed_rotation() {
+        let axis = Vec3::new(0.0, 1.0, 0.0);
+        let animation = TransformAnimation::keyframes(vec![
+            Keyframe { time: 1.0, value: Transform::from_axis_angle(Vec3::zeros(), axis, 90.0) },
+            Keyframe { time: 0.0, value: Transform::identity() },
+            Keyframe { time: 2.0, value: Transform::from_axis_angle(Vec3::zeros(), axis, 180.0) },
+        ]);
+
+        let rotated = animation.evaluate(1.5).transform_vector(&Vec3::new(1.0, 0.0, 0.0));
+        let expected = Vec3::new(f32::cos(f32::to_radians(135.0)), 0.0, -f32::sin(f32::to_radians(135.0)));
+        assert!((rotated - expected).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_camera_spline() {
+        let pose = |x: f32, vertical_fov: f32| CameraPose { position: Vec3::new(x, 0.0, 0.0), target: Vec3::zeros(), vertical_fov };
+        let animation = CameraAnimation::keyframes(vec![
+            Keyframe { time: 0.0, value: pose(0.0, 40.0) },
+            Keyframe { time: 1.0, value: pose(1.0, 50.0) },
+            Keyframe { time: 2.0, value: pose(2.0, 60.0) },
+        ]);
+
+        // The spline passes through the keys and reproduces evenly spaced linear motion
+        assert!((animation.evaluate(1.0).position - Vec3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((animation.evaluate(1.5).position - Vec3::new(1.5, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!(f32::abs(animation.evaluate(0.5).vertical_fov - 45.0) < 1.0);
+        assert_eq!(animation.evaluate(5.0).vertical_fov, 60.0);
+    }
+}
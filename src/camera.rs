@@ -1,13 +1,20 @@
+pub mod perspective;
+pub mod orthographic;
+pub mod panoramic;
+pub mod aperture;
+pub mod realistic;
+pub mod stereo;
+
+use nalgebra_glm as glm;
 use nalgebra_glm::Vec3;
-use rand::{thread_rng, Rng};
 
 use crate::resolution::Resolution;
 use crate::interval::Interval;
 use crate::ray::Ray;
+use crate::tile::Tile;
+use aperture::ApertureShape;
 
-// const WORLD_FORWARD: Vec3   = Vec3::new(0.0, 0.0, 1.0);
-// const WORLD_RIGHT: Vec3     = Vec3::new(1.0, 0.0, 0.0);
-const WORLD_UP: Vec3        = Vec3::new(0.0, 1.0, 0.0);
+pub const WORLD_UP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
 
 #[derive(Debug, Clone, Copy)]
 struct PixelDelta(Vec3, Vec3);
@@ -29,36 +36,100 @@ impl PixelDelta {
 #[derive(Debug, Clone, Copy)]
 struct ViewPlane {
     viewport_top_left: Vec3,
+    viewport_u: Vec3,
+    viewport_v: Vec3,
     pixel_delta: PixelDelta,
 }
 
 impl ViewPlane {
     pub fn new(position: &Vec3, focal_length: f32, camera_vectors: &CameraVectors, viewport_width: f32, viewport_height: f32, resolution: &Resolution) -> Self {
-        let vec_u = viewport_width * camera_vectors.up();
-        let vec_v = -1.0 * viewport_height * camera_vectors.right();
+        let vec_u = viewport_width * camera_vectors.right();
+        let vec_v = -viewport_height * camera_vectors.up();
 
         let pixel_delta_u = vec_u / resolution.width() as f32;
         let pixel_delta_v = vec_v / resolution.height() as f32;
-        let viewport_top_left = position - (focal_length * camera_vectors.forward()) - (vec_u / 2.0) - (vec_v / 2.0);
+        let viewport_top_left = position + (focal_length * camera_vectors.forward()) - (vec_u / 2.0) - (vec_v / 2.0);
 
         ViewPlane {
             viewport_top_left,
+            viewport_u: vec_u,
+            viewport_v: vec_v,
             pixel_delta: PixelDelta::new(pixel_delta_u, pixel_delta_v),
         }
     }
 
-    pub fn get_pixel_center(&self, x: f32, y: f32) -> Vec3 {
-        self.viewport_top_left + self.get_pixel_offset(x, y)
+    /// Slide the viewport within its plane, in fractions of the viewport size with positive values moving right and up
+    pub fn shift(&mut self, horizontal: f32, vertical: f32) {
+        self.viewport_top_left += horizontal * self.viewport_u - vertical * self.viewport_v;
     }
 
-    pub fn get_pixel_offset(&self, x: f32, y: f32) -> Vec3 {
-        x * self.pixel_delta.u() + y * self.pixel_delta.v()
+    pub fn get_film_position(&self, film_x: f32, film_y: f32) -> Vec3 {
+        self.viewport_top_left + film_x * self.pixel_delta.u() + film_y * self.pixel_delta.v()
+    }
+}
+
+/// Plane of sharp focus, perpendicular to the view direction unless the lens is tilted
+#[derive(Debug, Clone, Copy)]
+struct FocalPlane {
+    point: Vec3,
+    normal: Vec3,
+}
+
+impl FocalPlane {
+    pub fn new(position: &Vec3, focal_length: f32, camera_vectors: &CameraVectors) -> Self {
+        FocalPlane {
+            point: position + focal_length * camera_vectors.forward(),
+            normal: camera_vectors.forward(),
+        }
+    }
+
+    /// Scheimpflug tilt in degrees, positive values lean the top and the right of the plane away from the camera
+    pub fn tilt(&mut self, vertical: f32, horizontal: f32, camera_vectors: &CameraVectors) {
+        let normal = glm::rotate_vec3(&camera_vectors.forward(), -f32::to_radians(vertical), &camera_vectors.right());
+        self.normal = glm::rotate_vec3(&normal, f32::to_radians(horizontal), &camera_vectors.up());
+    }
+
+    /// Point in focus along the ray through the lens center towards `film_position`
+    pub fn focus_point(&self, position: &Vec3, film_position: &Vec3) -> Vec3 {
+        let direction = film_position - position;
+        let denominator = direction.dot(&self.normal);
+
+        // Rays running along a steeply tilted plane never reach it, keep them focused on the view plane
+        if denominator <= 1e-6 {
+            return *film_position
+        }
+
+        position + direction * ((self.point - position).dot(&self.normal) / denominator)
+    }
+}
+
+/// Shutter and aperture settings every camera embeds, set through `CameraBuilder`
+#[derive(Debug, Clone)]
+pub struct CameraControls {
+    shutter: Interval,
+    aperture: ApertureShape,
+}
+
+/// Builder methods shared by the cameras
+pub trait CameraBuilder: Sized {
+    fn controls_mut(&mut self) -> &mut CameraControls;
+
+    /// Open the shutter from `open` to `close`, spreading rays over time for motion blur
+    fn with_shutter(mut self, open: f32, close: f32) -> Self {
+        self.controls_mut().shutter = Interval::new(open, close);
+        self
+    }
+
+    /// Replace the circular lens aperture, shaping out of focus highlights.
+    /// Pinhole cameras ignore it, the realistic camera's aperture comes from its lens prescription
+    fn with_aperture(mut self, aperture: ApertureShape) -> Self {
+        self.controls_mut().aperture = aperture;
+        self
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 struct DefocusDisk {
-    angle: f32,
     radius: f32,
 }
 
@@ -66,48 +137,90 @@ impl DefocusDisk {
     pub fn new(angle: f32, focal_length: f32) -> Self {
         let radius = focal_length * f32::tan(f32::to_radians(angle / 2.0));
 
+        DefocusDisk::from_radius(radius)
+    }
+
+    pub fn from_radius(radius: f32) -> Self {
         DefocusDisk {
-            angle,
-            radius,
+            radius: f32::max(radius, 0.0),
         }
     }
 
-    pub fn angle(&self) -> f32 {
-        self.angle
+    pub fn is_pinhole(&self) -> bool {
+        self.radius <= 0.0
     }
 
-    pub fn sample(&self, camera_vectors: &CameraVectors) -> Vec3 {
+    pub fn sample(&self, camera_vectors: &CameraVectors, aperture: &ApertureShape) -> Vec3 {
         let disk_u = self.radius * camera_vectors.right();
-        let disk_v = -1.0 * self.radius * camera_vectors.up();
-
-        let random_sample = self.random_in_unit_disk();
-        (random_sample.x * disk_u) + (random_sample.y * disk_v)
-    }
-
-    fn random_in_unit_disk(&self) -> Vec3 {
-        let mut rng = thread_rng();
+        let disk_v = self.radius * camera_vectors.up();
 
-        loop {
-            let vec = Vec3::new(
-                rng.gen_range(-1.0..1.0),
-                rng.gen_range(-1.0..1.0),
-                0.0
-            );
-
-            if vec.magnitude_squared() < 1.0 {
-                return vec
-            }
-        }
+        let (x, y) = aperture.sample();
+        (x * disk_u) + (y * disk_v)
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 struct CameraVectors {
     forward: Vec3,
+    right: Vec3,
     up: Vec3,
 }
 
 impl CameraVectors {
+    pub fn new(position: &Vec3, orientation: &CameraOrientation) -> Result<Self, CameraError> {
+        let camera_vectors = match *orientation {
+            CameraOrientation::LookAt { target, up, roll } => {
+                let view_direction = target - position;
+                if view_direction.magnitude_squared() < 1e-12 {
+                    return Err(CameraError::ZeroLengthViewDirection)
+                }
+
+                let forward = view_direction.normalize();
+                let right = forward.cross(&up);
+                if right.magnitude_squared() < 1e-12 {
+                    return Err(CameraError::UpParallelToViewDirection)
+                }
+
+                let right = right.normalize();
+                CameraVectors::from_basis(forward, right).rolled(roll)
+            },
+            CameraOrientation::YawPitchRoll { yaw, pitch, roll } => {
+                let (yaw, pitch) = (f32::to_radians(yaw), f32::to_radians(pitch));
+
+                // Yaw turns around the world up axis, so the horizontal right vector is always well defined
+                let forward = Vec3::new(-f32::sin(yaw) * f32::cos(pitch), f32::sin(pitch), -f32::cos(yaw) * f32::cos(pitch));
+                let right = Vec3::new(f32::cos(yaw), 0.0, -f32::sin(yaw));
+                CameraVectors::from_basis(forward, right).rolled(roll)
+            },
+        };
+
+        let is_finite = |v: &Vec3| v.iter().all(|c| c.is_finite());
+        if !is_finite(&camera_vectors.forward) || !is_finite(&camera_vectors.right) || !is_finite(&camera_vectors.up) {
+            return Err(CameraError::NonFiniteOrientation)
+        }
+
+        Ok(camera_vectors)
+    }
+
+    fn from_basis(forward: Vec3, right: Vec3) -> Self {
+        CameraVectors {
+            forward,
+            right,
+            up: right.cross(&forward),
+        }
+    }
+
+    /// Rotate the camera counter-clockwise around its view direction, in degrees
+    fn rolled(self, roll: f32) -> Self {
+        let (sin, cos) = f32::sin_cos(f32::to_radians(roll));
+
+        CameraVectors {
+            forward: self.forward,
+            right: cos * self.right + sin * self.up,
+            up: cos * self.up - sin * self.right,
+        }
+    }
+
     pub fn forward(&self) -> Vec3 {
         self.forward
     }
@@ -117,98 +230,319 @@ impl CameraVectors {
     }
 
     pub fn right(&self) -> Vec3 {
-        self.forward.cross(&self.up)
+        self.right
+    }
+
+    /// Transform a camera space direction (x right, y up, looking down -z) to world space
+    pub fn local_to_world(&self, local: &Vec3) -> Vec3 {
+        local.x * self.right() + local.y * self.up() - local.z * self.forward()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CameraOrientation {
+    LookAt { target: Vec3, up: Vec3, roll: f32 },       // Roll in degrees
+    YawPitchRoll { yaw: f32, pitch: f32, roll: f32 },   // Degrees, yaw 0 and pitch 0 look down -z
+}
+
+impl CameraOrientation {
+    /// Look at a target with the world up vector and no roll
+    pub fn look_at(target: Vec3) -> Self {
+        CameraOrientation::LookAt {
+            target,
+            up: WORLD_UP,
+            roll: 0.0,
+        }
+    }
+
+    fn target(&self) -> Option<Vec3> {
+        match *self {
+            CameraOrientation::LookAt { target, .. } => Some(target),
+            CameraOrientation::YawPitchRoll { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraError {
+    ZeroLengthViewDirection,
+    UpParallelToViewDirection,
+    NonFiniteOrientation,
+    AutoFocusWithoutTarget,
+    UnfocusableLens,
+}
+
+impl std::fmt::Display for CameraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CameraError::ZeroLengthViewDirection => write!(f, "camera position and look at target coincide"),
+            CameraError::UpParallelToViewDirection => write!(f, "camera up vector is parallel to the view direction"),
+            CameraError::NonFiniteOrientation => write!(f, "camera orientation contains non-finite values"),
+            CameraError::AutoFocusWithoutTarget => write!(f, "auto focus requires a look at target"),
+            CameraError::UnfocusableLens => write!(f, "lens cannot focus at the requested distance"),
+        }
     }
 }
 
+impl std::error::Error for CameraError {}
+
 pub enum FocusMode {
     AutoFocus,
     Manual(f32),
 }
 
+impl FocusMode {
+    fn focal_length(&self, position: &Vec3, orientation: &CameraOrientation) -> Result<f32, CameraError> {
+        match *self {
+            FocusMode::AutoFocus => {
+                let target = orientation.target().ok_or(CameraError::AutoFocusWithoutTarget)?;
+                Ok((target - position).magnitude())
+            },
+            FocusMode::Manual(length) => Ok(length),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
-pub struct Camera {
-    position: Vec3,
-    scene_depth: Interval,
-    view_plane: ViewPlane,
-    defocus_disk: DefocusDisk,
-    camera_vectors: CameraVectors,
-}
-
-impl Camera {
-    pub fn new(position: Vec3, look_at: Vec3, vertical_fov: f32, focus_mode: FocusMode, defocus_angle: f32, scene_depth: Interval, resolution: &Resolution) -> Self {
-        let focal_length = match focus_mode {
-            FocusMode::AutoFocus => (look_at - position).magnitude(),
-            FocusMode::Manual(length) => length,
-        };
+pub struct SensorSize {
+    pub width: f32,     // Millimeters
+    pub height: f32,    // Millimeters
+}
 
-        let (viewport_width, viewport_height) = Self::calculate_viewport_extent(vertical_fov, focal_length, resolution);
+impl SensorSize {
+    pub const FULL_FRAME: SensorSize = SensorSize { width: 36.0, height: 24.0 };
+    pub const APS_C: SensorSize = SensorSize { width: 23.6, height: 15.6 };
+    pub const MICRO_FOUR_THIRDS: SensorSize = SensorSize { width: 17.3, height: 13.0 };
+}
 
-        let forward = (position - look_at).normalize();
-        let up = WORLD_UP.cross(&forward);
-        let camera_vectors = CameraVectors {
-            forward,
-            up,
-        };
-        
-        let view_plane = ViewPlane::new(&position, focal_length, &camera_vectors, viewport_width, viewport_height, resolution);
-        let defocus_disk = DefocusDisk::new(defocus_angle, focal_length);
+/// Real camera settings, scene units are assumed to be meters
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicalCameraSettings {
+    pub sensor: SensorSize,
+    pub focal_length: f32,  // Lens focal length in millimeters
+    pub f_number: f32,
+    pub shutter_speed: f32, // Seconds
+    pub iso: f32,
+}
 
-        Camera {
-            position,
-            scene_depth,
-            view_plane,
-            defocus_disk,
-            camera_vectors,
-        }
+impl PhysicalCameraSettings {
+    /// Vertical field of view in degrees, with the sensor width fitted to the image width
+    pub fn vertical_fov(&self, resolution: &Resolution) -> f32 {
+        let sensor_height = self.sensor.width / resolution.aspect_ratio();
+
+        f32::to_degrees(2.0 * f32::atan(sensor_height / (2.0 * self.focal_length)))
     }
 
-    pub fn scene_depth_interval(&self) -> &Interval {
-        &self.scene_depth
+    /// Radius of the entrance pupil in meters
+    pub fn aperture_radius(&self) -> f32 {
+        let aperture_diameter = self.focal_length / self.f_number;
+
+        0.5 * aperture_diameter / 1000.0
     }
 
-    pub fn get_primary_ray(&self, x: u32, y: u32, sample: u32) -> Ray {
-        let pixel_center = self.get_pixel_center(x, y);
-        let pixel_sample = self.sample_pixel(pixel_center, sample);
+    /// Scale from scene luminance to normalized sensor response, using the saturation based EV100 model
+    pub fn exposure(&self) -> f32 {
+        let ev100 = f32::log2((self.f_number * self.f_number) / self.shutter_speed * 100.0 / self.iso);
+
+        1.0 / (1.2 * f32::powf(2.0, ev100))
+    }
+}
 
-        let ray_origin = self.get_ray_origin();
-        let ray_direction = pixel_sample - ray_origin;
-        let ray_direction = ray_direction.normalize();
+/// Position on the film to generate a camera ray for, pixel (x, y) spans [x, x + 1) x [y, y + 1)
+#[derive(Debug, Clone, Copy)]
+pub struct CameraSample {
+    pub film_x: f32,
+    pub film_y: f32,
+    pub time: f32,  // Within the camera's shutter interval
+}
 
-        Ray::new(ray_origin, ray_direction)
+#[derive(Debug)]
+pub struct CameraRay {
+    pub ray: Ray,
+    pub weight: f32,    // Scales the radiance carried back along the ray
+}
+
+pub trait Camera {
+    /// Generate a ray for a film sample, `None` if the camera does not cover the sample position
+    fn generate_ray(&self, sample: &CameraSample) -> Option<CameraRay>;
+
+    fn scene_depth_interval(&self) -> &Interval;
+
+    /// Times between which the shutter is open, rays are spread uniformly over this interval
+    fn shutter_interval(&self) -> Interval {
+        Interval::new(0.0, 0.0)
     }
 
-    fn get_pixel_center(&self, x: u32, y: u32) -> Vec3 {
-        self.view_plane.get_pixel_center(x as f32, y as f32)
+    /// Film regions holding separate images, the reconstruction filter never spreads a sample past its region
+    fn film_regions(&self) -> Vec<Tile> {
+        Vec::new()
     }
+}
 
-    fn sample_pixel(&self, pixel_center: Vec3, _sample_num: u32) -> Vec3 {
-        // Sampling is random for now -> use AA sample grid for consistent sampling
-        let mut rng = thread_rng();
+impl Default for CameraControls {
+    fn default() -> Self {
+        CameraControls {
+            shutter: Interval::new(0.0, 0.0),
+            aperture: ApertureShape::Circular,
+        }
+    }
+}
 
-        let x_offset = -0.5 + rng.gen_range(0.0..1.0);
-        let y_offset = -0.5 + rng.gen_range(0.0..1.0);
+impl CameraControls {
+    pub fn shutter(&self) -> Interval {
+        self.shutter
+    }
 
-        let sample_offset = self.view_plane.get_pixel_offset(x_offset, y_offset);
-        pixel_center + sample_offset
+    pub fn aperture(&self) -> &ApertureShape {
+        &self.aperture
     }
+}
 
-    fn get_ray_origin(&self) -> Vec3 {
-        if self.defocus_disk.angle() <= 0.0 {
-            self.position
+impl CameraRay {
+    pub fn new(ray: Ray) -> Self {
+        CameraRay {
+            ray,
+            weight: 1.0,
         }
-        else {
-            self.position + self.defocus_disk.sample(&self.camera_vectors)
+    }
+}
+
+fn calculate_viewport_extent(vertical_fov: f32, focal_length: f32, resolution: &Resolution) -> (f32, f32) {
+    let fov_radians = f32::to_radians(vertical_fov);
+    let height = f32::tan(fov_radians / 2.0);
+
+    let viewport_height = 2.0 * height * focal_length;
+    let viewport_width = viewport_height * resolution.aspect_ratio();
+
+    (viewport_width, viewport_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_orthonormal(camera_vectors: &CameraVectors) {
+        let (f, r, u) = (camera_vectors.forward(), camera_vectors.right(), camera_vectors.up());
+
+        for v in [f, r, u] {
+            assert!(f32::abs(v.magnitude() - 1.0) < 1e-5);
+        }
+
+        assert!(f32::abs(f.dot(&r)) < 1e-5);
+        assert!(f32::abs(f.dot(&u)) < 1e-5);
+        assert!(f32::abs(r.dot(&u)) < 1e-5);
+    }
+
+    #[test]
+    fn test_tilted_focal_plane() {
+        let position = Vec3::new(0.0, 0.0, 5.0);
+        let camera_vectors = CameraVectors::new(&position, &CameraOrientation::look_at(Vec3::zeros())).unwrap();
+        let mut focal_plane = FocalPlane::new(&position, 5.0, &camera_vectors);
+
+        let film_position = Vec3::new(0.0, 1.0, 0.0);
+        assert!((focal_plane.focus_point(&position, &film_position) - film_position).magnitude() < 1e-5);
+
+        // Leaning the top away moves focus above the center further from the camera
+        focal_plane.tilt(30.0, 0.0, &camera_vectors);
+        let focus_point = focal_plane.focus_point(&position, &film_position);
+        assert!(focus_point.z < 0.0);
+        assert!(f32::abs((focus_point - focal_plane.point).dot(&focal_plane.normal)) < 1e-4);
+    }
+
+    #[test]
+    fn test_look_at() {
+        let camera_vectors = CameraVectors::new(&Vec3::new(0.0, 0.0, 5.0), &CameraOrientation::look_at(Vec3::zeros())).unwrap();
+
+        assert_orthonormal(&camera_vectors);
+        assert!((camera_vectors.forward() - Vec3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+        assert!((camera_vectors.right() - Vec3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((camera_vectors.up() - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_look_straight_down() {
+        let position = Vec3::new(0.0, 5.0, 0.0);
+
+        let degenerate = CameraVectors::new(&position, &CameraOrientation::look_at(Vec3::zeros()));
+        assert_eq!(degenerate.unwrap_err(), CameraError::UpParallelToViewDirection);
+
+        let explicit_up = CameraOrientation::LookAt { target: Vec3::zeros(), up: Vec3::new(0.0, 0.0, -1.0), roll: 0.0 };
+        let camera_vectors = CameraVectors::new(&position, &explicit_up).unwrap();
+        assert_orthonormal(&camera_vectors);
+        assert!((camera_vectors.up() - Vec3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_zero_length_view_direction() {
+        let position = Vec3::new(1.0, 2.0, 3.0);
+
+        let result = CameraVectors::new(&position, &CameraOrientation::look_at(position));
+        assert_eq!(result.unwrap_err(), CameraError::ZeroLengthViewDirection);
+    }
+
+    #[test]
+    fn test_yaw_pitch_roll() {
+        let position = Vec3::zeros();
+
+        let turned_left = CameraVectors::new(&position, &CameraOrientation::YawPitchRoll { yaw: 90.0, pitch: 0.0, roll: 0.0 }).unwrap();
+        assert_orthonormal(&turned_left);
+        assert!((turned_left.forward() - Vec3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-5);
+
+        let straight_up = CameraVectors::new(&position, &CameraOrientation::YawPitchRoll { yaw: 0.0, pitch: 90.0, roll: 0.0 }).unwrap();
+        assert_orthonormal(&straight_up);
+        assert!((straight_up.forward() - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+
+        let rolled = CameraVectors::new(&position, &CameraOrientation::YawPitchRoll { yaw: 0.0, pitch: 0.0, roll: 90.0 }).unwrap();
+        assert_orthonormal(&rolled);
+        assert!((rolled.right() - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+        assert!((rolled.up() - Vec3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_physical_settings() {
+        let settings = PhysicalCameraSettings {
+            sensor: SensorSize::FULL_FRAME,
+            focal_length: 50.0,
+            f_number: 2.0,
+            shutter_speed: 1.0 / 125.0,
+            iso: 100.0,
+        };
+
+        // A 50mm lens on a 3:2 full frame sensor covers about 27 degrees vertically
+        assert!(f32::abs(settings.vertical_fov(&Resolution::new(1500, 1000)) - 26.99) < 0.01);
+        assert!(f32::abs(settings.aperture_radius() - 0.0125) < 1e-6);
+
+        // Doubling ISO or exposure time doubles the sensor response, opening up one stop does the same
+        let brighter_iso = PhysicalCameraSettings { iso: 200.0, ..settings };
+        let brighter_shutter = PhysicalCameraSettings { shutter_speed: 2.0 / 125.0, ..settings };
+        let brighter_aperture = PhysicalCameraSettings { f_number: 2.0 / f32::sqrt(2.0), ..settings };
+        for brighter in [brighter_iso, brighter_shutter, brighter_aperture] {
+            assert!(f32::abs(brighter.exposure() / settings.exposure() - 2.0) < 1e-4);
         }
     }
 
-    fn calculate_viewport_extent(vertical_fov: f32, focal_length: f32, resolution: &Resolution) -> (f32, f32) {
-        let fov_radians = f32::to_radians(vertical_fov);
-        let height = f32::tan(fov_radians / 2.0);
+    #[test]
+    fn test_auto_focus_requires_target() {
+        let orientation = CameraOrientation::YawPitchRoll { yaw: 0.0, pitch: 0.0, roll: 0.0 };
 
-        let viewport_height = 2.0 * height * focal_length;
-        let viewport_width = viewport_height * resolution.aspect_ratio();
+        assert_eq!(FocusMode::AutoFocus.focal_length(&Vec3::zeros(), &orientation), Err(CameraError::AutoFocusWithoutTarget));
+        assert_eq!(FocusMode::Manual(2.0).focal_length(&Vec3::zeros(), &orientation), Ok(2.0));
+    }
+
+    #[test]
+    fn test_shared_controls() {
+        let resolution = Resolution::new(4, 2);
+        let orientation = CameraOrientation::look_at(Vec3::zeros());
 
-        (viewport_width, viewport_height)
+        let perspective = perspective::PerspectiveCamera::new(Vec3::new(0.0, 0.0, 1.0), orientation, 60.0, FocusMode::AutoFocus, 0.0, Interval::new(0.001, 100.0), &resolution)
+            .unwrap()
+            .with_shutter(0.25, 0.5);
+        let panoramic = panoramic::PanoramicCamera::new(Vec3::new(0.0, 0.0, 1.0), orientation, panoramic::PanoramicProjection::Equirectangular, Interval::new(0.001, 100.0), &resolution)
+            .unwrap()
+            .with_shutter(0.25, 0.5);
+
+        for shutter in [perspective.shutter_interval(), panoramic.shutter_interval()] {
+            assert_eq!((shutter.min(), shutter.max()), (0.25, 0.5));
+        }
     }
 }
@@ -0,0 +1,141 @@
+use std::f32::consts::{PI, FRAC_PI_2};
+use std::path::Path;
+use std::sync::Arc;
+use rand::{thread_rng, Rng};
+use image::ImageResult;
+
+use crate::sampling::Distribution1D;
+
+#[derive(Debug, Clone, Default)]
+pub enum ApertureShape {
+    #[default]
+    Circular,
+    Polygonal { blades: u32, rotation: f32 },   // Rotation in degrees, at 0 a corner points up
+    Mask(Arc<ApertureMask>),
+}
+
+/// Aperture transmission loaded from an image, brighter pixels let through more light
+#[derive(Debug)]
+pub struct ApertureMask {
+    width: u32,
+    height: u32,
+    distribution: Distribution1D,
+}
+
+impl ApertureShape {
+    /// Sample a point on the aperture, within [-1, 1] x [-1, 1] with y pointing up
+    pub fn sample(&self) -> (f32, f32) {
+        match self {
+            ApertureShape::Circular => Self::random_in_unit_disk(),
+            ApertureShape::Polygonal { blades, rotation } => Self::random_in_polygon(*blades, *rotation),
+            ApertureShape::Mask(mask) => mask.sample(),
+        }
+    }
+
+    fn random_in_unit_disk() -> (f32, f32) {
+        let mut rng = thread_rng();
+
+        loop {
+            let (x, y): (f32, f32) = (rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+
+            if x * x + y * y < 1.0 {
+                return (x, y)
+            }
+        }
+    }
+
+    fn random_in_polygon(blades: u32, rotation: f32) -> (f32, f32) {
+        if blades < 3 {
+            return Self::random_in_unit_disk()
+        }
+
+        let mut rng = thread_rng();
+        let sector_angle = 2.0 * PI / blades as f32;
+        let sector = rng.gen_range(0..blades) as f32;
+
+        // Uniformly sample the triangle between the center and two neighbouring corners
+        let start_angle = FRAC_PI_2 + f32::to_radians(rotation) + sector * sector_angle;
+        let (a, b) = (
+            (f32::cos(start_angle), f32::sin(start_angle)),
+            (f32::cos(start_angle + sector_angle), f32::sin(start_angle + sector_angle)),
+        );
+
+        let (mut u, mut v): (f32, f32) = (rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+        if u + v > 1.0 {
+            (u, v) = (1.0 - u, 1.0 - v);
+        }
+
+        (u * a.0 + v * b.0, u * a.1 + v * b.1)
+    }
+}
+
+impl ApertureMask {
+    pub fn load(path: &Path) -> ImageResult<Self> {
+        let image = image::open(path)?.to_luma32f();
+        let (width, height) = image.dimensions();
+
+        Ok(ApertureMask::new(width, height, image.into_raw()))
+    }
+
+    /// Build a mask from row major transmission values, top row first
+    pub fn new(width: u32, height: u32, transmission: Vec<f32>) -> Self {
+        assert_eq!(transmission.len(), (width * height) as usize, "Mask size does not match its dimensions");
+
+        ApertureMask {
+            width,
+            height,
+            distribution: Distribution1D::new(transmission),
+        }
+    }
+
+    pub fn sample(&self) -> (f32, f32) {
+        let mut rng = thread_rng();
+        let (pixel, _, _) = self.distribution.sample_discrete(rng.gen_range(0.0..1.0));
+
+        let column = (pixel as u32 % self.width) as f32 + rng.gen_range(0.0..1.0);
+        let row = (pixel as u32 / self.width) as f32 + rng.gen_range(0.0..1.0);
+
+        // Fit the mask in the unit square, keeping its aspect ratio
+        let scale = u32::max(self.width, self.height) as f32;
+        let x = (2.0 * column - self.width as f32) / scale;
+        let y = (self.height as f32 - 2.0 * row) / scale;
+
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygon_samples_inside() {
+        let shape = ApertureShape::Polygonal { blades: 6, rotation: 0.0 };
+
+        // A hexagon with corners on the unit circle contains the circle of radius cos(30 deg)
+        let inner_radius = f32::cos(PI / 6.0);
+        let mut outside_inner_circle = 0;
+        for _ in 0..1000 {
+            let (x, y) = shape.sample();
+            let radius = f32::sqrt(x * x + y * y);
+
+            assert!(radius <= 1.0 + 1e-5);
+            if radius > inner_radius {
+                outside_inner_circle += 1;
+            }
+        }
+
+        assert!(outside_inner_circle > 0);
+    }
+
+    #[test]
+    fn test_mask_only_samples_open_pixels() {
+        // 2x2 mask with only the top right pixel open
+        let mask = ApertureMask::new(2, 2, vec![0.0, 1.0, 0.0, 0.0]);
+
+        for _ in 0..100 {
+            let (x, y) = mask.sample();
+            assert!((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use nalgebra_glm::Vec3;
+
+use super::{Camera, CameraBuilder, CameraControls, CameraOrientation, CameraError, CameraSample, CameraRay, CameraVectors, ViewPlane, DefocusDisk, FocusMode};
+use crate::resolution::Resolution;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+#[derive(Debug, Clone)]
+pub struct OrthographicCamera {
+    focal_length: f32,
+    scene_depth: Interval,
+    controls: CameraControls,
+    view_plane: ViewPlane,
+    defocus_disk: DefocusDisk,
+    camera_vectors: CameraVectors,
+}
+
+impl OrthographicCamera {
+    /// `view_width` is the width of the view volume in world units
+    pub fn new(position: Vec3, orientation: CameraOrientation, view_width: f32, focus_mode: FocusMode, defocus_angle: f32, scene_depth: Interval, resolution: &Resolution) -> Result<Self, CameraError> {
+        let focal_length = focus_mode.focal_length(&position, &orientation)?;
+        let (viewport_width, viewport_height) = (view_width, view_width / resolution.aspect_ratio());
+
+        let camera_vectors = CameraVectors::new(&position, &orientation)?;
+        let view_plane = ViewPlane::new(&position, focal_length, &camera_vectors, viewport_width, viewport_height, resolution);
+        let defocus_disk = DefocusDisk::new(defocus_angle, focal_length);
+
+        Ok(OrthographicCamera {
+            focal_length,
+            scene_depth,
+            controls: CameraControls::default(),
+            view_plane,
+            defocus_disk,
+            camera_vectors,
+        })
+    }
+
+    fn get_ray_origin(&self, pixel_sample: &Vec3) -> Vec3 {
+        // Parallel rays: project the view plane sample back onto the camera plane
+        let lens_center = pixel_sample - self.focal_length * self.camera_vectors.forward();
+
+        if self.defocus_disk.is_pinhole() {
+            lens_center
+        }
+        else {
+            lens_center + self.defocus_disk.sample(&self.camera_vectors, self.controls.aperture())
+        }
+    }
+}
+
+impl CameraBuilder for OrthographicCamera {
+    fn controls_mut(&mut self) -> &mut CameraControls {
+        &mut self.controls
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<CameraRay> {
+        let pixel_sample = self.view_plane.get_film_position(sample.film_x, sample.film_y);
+
+        let ray_origin = self.get_ray_origin(&pixel_sample);
+        let ray_direction = pixel_sample - ray_origin;
+        let ray_direction = ray_direction.normalize();
+
+        Some(CameraRay::new(Ray::with_time(ray_origin, ray_direction, sample.time)))
+    }
+
+    fn scene_depth_interval(&self) -> &Interval {
+        &self.scene_depth
+    }
+
+    fn shutter_interval(&self) -> Interval {
+        self.controls.shutter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rays_are_parallel() {
+        let camera = OrthographicCamera::new(
+            Vec3::new(0.0, 0.0, 5.0), CameraOrientation::look_at(Vec3::zeros()),
+            4.0, FocusMode::AutoFocus, 0.0,
+            Interval::new(0.001, 100.0), &Resolution::new(200, 100)
+        ).unwrap();
+
+        let top_left = camera.generate_ray(&CameraSample { film_x: 0.0, film_y: 0.0, time: 0.0 }).unwrap().ray;
+        let bottom_right = camera.generate_ray(&CameraSample { film_x: 200.0, film_y: 100.0, time: 0.0 }).unwrap().ray;
+
+        assert!((top_left.direction() - bottom_right.direction()).magnitude() < 1e-5);
+        assert!((top_left.direction() - Vec3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+        assert!(f32::abs((top_left.origin() - bottom_right.origin()).x.abs() - 4.0) < 1e-4);
+        assert!(f32::abs((top_left.origin() - bottom_right.origin()).y.abs() - 2.0) < 1e-4);
+    }
+}
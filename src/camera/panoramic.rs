@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+
+use super::{Camera, CameraBuilder, CameraControls, CameraOrientation, CameraError, CameraSample, CameraRay, CameraVectors};
+use crate::resolution::Resolution;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+#[derive(Debug, Clone, Copy)]
+pub enum FisheyeMapping {
+    Equidistant,    // Image radius proportional to the angle from the view axis
+    Equisolid,      // Equal area, image radius proportional to sin(angle / 2)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PanoramicProjection {
+    Equirectangular,                                // Full 360 x 180 degree lat-long panorama, use a 2:1 resolution
+    Fisheye { fov: f32, mapping: FisheyeMapping },  // Circular image inscribed in the frame
+    CubeMap,    // Horizontal strip of six square faces ordered +X, -X, +Y, -Y, +Z, -Z, use a 6:1 resolution
+}
+
+#[derive(Debug, Clone)]
+pub struct PanoramicCamera {
+    position: Vec3,
+    projection: PanoramicProjection,
+    resolution: Resolution,
+    scene_depth: Interval,
+    controls: CameraControls,
+    camera_vectors: CameraVectors,
+}
+
+impl PanoramicCamera {
+    pub fn new(position: Vec3, orientation: CameraOrientation, projection: PanoramicProjection, scene_depth: Interval, resolution: &Resolution) -> Result<Self, CameraError> {
+        Ok(PanoramicCamera {
+            position,
+            projection,
+            resolution: *resolution,
+            scene_depth,
+            controls: CameraControls::default(),
+            camera_vectors: CameraVectors::new(&position, &orientation)?,
+        })
+    }
+
+    fn equirectangular_direction(u: f32, v: f32) -> Vec3 {
+        let longitude = (u - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - v) * PI;
+
+        Vec3::new(
+            f32::cos(latitude) * f32::sin(longitude),
+            f32::sin(latitude),
+            -f32::cos(latitude) * f32::cos(longitude),
+        )
+    }
+
+    fn fisheye_direction(u: f32, v: f32, fov: f32, mapping: FisheyeMapping, aspect_ratio: f32) -> Option<Vec3> {
+        // Normalized image circle coordinates, the circle fits the shorter image side
+        let (scale_x, scale_y) = if aspect_ratio >= 1.0 { (aspect_ratio, 1.0) } else { (1.0, 1.0 / aspect_ratio) };
+        let x = (2.0 * u - 1.0) * scale_x;
+        let y = (1.0 - 2.0 * v) * scale_y;
+
+        let radius = f32::sqrt(x * x + y * y);
+        if radius > 1.0 {
+            return None
+        }
+
+        let max_theta = f32::to_radians(fov) / 2.0;
+        let theta = match mapping {
+            FisheyeMapping::Equidistant => radius * max_theta,
+            FisheyeMapping::Equisolid => 2.0 * f32::asin(f32::min(radius * f32::sin(max_theta / 2.0), 1.0)),
+        };
+        let phi = f32::atan2(y, x);
+
+        Some(Vec3::new(
+            f32::sin(theta) * f32::cos(phi),
+            f32::sin(theta) * f32::sin(phi),
+            -f32::cos(theta),
+        ))
+    }
+
+    fn cube_map_direction(u: f32, v: f32) -> Vec3 {
+        let face = f32::min(f32::floor(u * 6.0), 5.0);
+        let s = 2.0 * (u * 6.0 - face) - 1.0;
+        let t = 2.0 * v - 1.0;
+
+        // OpenGL cube map face orientations
+        match face as u32 {
+            0 => Vec3::new(1.0, -t, -s),
+            1 => Vec3::new(-1.0, -t, s),
+            2 => Vec3::new(s, 1.0, t),
+            3 => Vec3::new(s, -1.0, -t),
+            4 => Vec3::new(s, -t, 1.0),
+            _ => Vec3::new(-s, -t, -1.0),
+        }
+    }
+}
+
+impl CameraBuilder for PanoramicCamera {
+    fn controls_mut(&mut self) -> &mut CameraControls {
+        &mut self.controls
+    }
+}
+
+impl Camera for PanoramicCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<CameraRay> {
+        let u = sample.film_x / self.resolution.width() as f32;
+        let v = sample.film_y / self.resolution.height() as f32;
+
+        let direction = match self.projection {
+            PanoramicProjection::Equirectangular => Self::equirectangular_direction(u, v),
+            PanoramicProjection::Fisheye { fov, mapping } => Self::fisheye_direction(u, v, fov, mapping, self.resolution.aspect_ratio())?,
+            PanoramicProjection::CubeMap => Self::cube_map_direction(u, v),
+        };
+
+        let direction = self.camera_vectors.local_to_world(&direction).normalize();
+        Some(CameraRay::new(Ray::with_time(self.position, direction, sample.time)))
+    }
+
+    fn scene_depth_interval(&self) -> &Interval {
+        &self.scene_depth
+    }
+
+    fn shutter_interval(&self) -> Interval {
+        self.controls.shutter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_camera(projection: PanoramicProjection) -> PanoramicCamera {
+        PanoramicCamera::new(
+            Vec3::new(0.0, 0.0, 5.0), CameraOrientation::look_at(Vec3::zeros()),
+            projection,
+            Interval::new(0.001, 100.0), &Resolution::new(200, 100)
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_equirectangular_center_looks_forward() {
+        let camera = test_camera(PanoramicProjection::Equirectangular);
+
+        let center = camera.generate_ray(&CameraSample { film_x: 100.0, film_y: 50.0, time: 0.0 }).unwrap().ray;
+        let top = camera.generate_ray(&CameraSample { film_x: 100.0, film_y: 0.0, time: 0.0 }).unwrap().ray;
+        let left_edge = camera.generate_ray(&CameraSample { film_x: 0.0, film_y: 50.0, time: 0.0 }).unwrap().ray;
+
+        assert!((center.direction() - Vec3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+        assert!((top.direction() - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+        assert!((left_edge.direction() - Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_fisheye_image_circle() {
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let camera = test_camera(PanoramicProjection::Fisheye { fov: 180.0, mapping });
+
+            assert!(camera.generate_ray(&CameraSample { film_x: 0.0, film_y: 0.0, time: 0.0 }).is_none());
+
+            let center = camera.generate_ray(&CameraSample { film_x: 100.0, film_y: 50.0, time: 0.0 }).unwrap().ray;
+            assert!((center.direction() - Vec3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+
+            // The edge of the image circle is at half the field of view
+            let edge = camera.generate_ray(&CameraSample { film_x: 150.0, film_y: 50.0, time: 0.0 }).unwrap().ray;
+            assert!(f32::abs(edge.direction().dot(&Vec3::new(0.0, 0.0, -1.0))) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_cube_map_face_centers() {
+        let camera = test_camera(PanoramicProjection::CubeMap);
+        let face_width = 200.0 / 6.0;
+
+        let expected = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+
+        for (face, direction) in expected.iter().enumerate() {
+            let ray = camera.generate_ray(&CameraSample { film_x: (face as f32 + 0.5) * face_width, film_y: 50.0, time: 0.0 }).unwrap().ray;
+            assert!((ray.direction() - direction).magnitude() < 1e-5, "face {}: {:?}", face, ray.direction());
+        }
+    }
+}
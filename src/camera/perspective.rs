@@ -0,0 +1,177 @@
+use nalgebra_glm::Vec3;
+
+use super::{Camera, CameraBuilder, CameraControls, CameraOrientation, CameraError, CameraSample, CameraRay, CameraVectors, ViewPlane, FocalPlane, DefocusDisk, FocusMode, PhysicalCameraSettings};
+use crate::resolution::Resolution;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+#[derive(Debug, Clone)]
+pub struct PerspectiveCamera {
+    position: Vec3,
+    scene_depth: Interval,
+    controls: CameraControls,
+    view_plane: ViewPlane,
+    focal_plane: FocalPlane,
+    defocus_disk: DefocusDisk,
+    camera_vectors: CameraVectors,
+    exposure: f32,
+}
+
+impl PerspectiveCamera {
+    pub fn new(position: Vec3, orientation: CameraOrientation, vertical_fov: f32, focus_mode: FocusMode, defocus_angle: f32, scene_depth: Interval, resolution: &Resolution) -> Result<Self, CameraError> {
+        let focal_length = focus_mode.focal_length(&position, &orientation)?;
+        let defocus_disk = DefocusDisk::new(defocus_angle, focal_length);
+
+        Self::with_lens(position, orientation, vertical_fov, focal_length, defocus_disk, scene_depth, resolution)
+    }
+
+    /// Camera driven by real camera settings: the sensor and focal length set the field of view and
+    /// the f-number sets the depth of field. Exposure is left alone, see `with_physical_exposure`
+    pub fn physical(position: Vec3, orientation: CameraOrientation, settings: &PhysicalCameraSettings, focus_mode: FocusMode, scene_depth: Interval, resolution: &Resolution) -> Result<Self, CameraError> {
+        let focus_distance = focus_mode.focal_length(&position, &orientation)?;
+        let defocus_disk = DefocusDisk::from_radius(settings.aperture_radius());
+
+        Self::with_lens(position, orientation, settings.vertical_fov(resolution), focus_distance, defocus_disk, scene_depth, resolution)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_lens(position: Vec3, orientation: CameraOrientation, vertical_fov: f32, focal_length: f32, defocus_disk: DefocusDisk, scene_depth: Interval, resolution: &Resolution) -> Result<Self, CameraError> {
+        let (viewport_width, viewport_height) = super::calculate_viewport_extent(vertical_fov, focal_length, resolution);
+
+        let camera_vectors = CameraVectors::new(&position, &orientation)?;
+        let view_plane = ViewPlane::new(&position, focal_length, &camera_vectors, viewport_width, viewport_height, resolution);
+        let focal_plane = FocalPlane::new(&position, focal_length, &camera_vectors);
+
+        Ok(PerspectiveCamera {
+            position,
+            scene_depth,
+            controls: CameraControls::default(),
+            view_plane,
+            focal_plane,
+            defocus_disk,
+            camera_vectors,
+            exposure: 1.0,
+        })
+    }
+
+    fn get_ray_origin(&self) -> Vec3 {
+        if self.defocus_disk.is_pinhole() {
+            self.position
+        }
+        else {
+            self.position + self.defocus_disk.sample(&self.camera_vectors, self.controls.aperture())
+        }
+    }
+
+    /// Shift the lens parallel to the sensor for an off-axis frustum, in fractions of the frame size.
+    /// Shifting up instead of pitching the camera keeps vertical lines parallel
+    pub fn with_lens_shift(mut self, horizontal: f32, vertical: f32) -> Self {
+        self.view_plane.shift(horizontal, vertical);
+        self
+    }
+
+    /// Tilt the plane of focus in degrees around the horizontal (`vertical`) and vertical (`horizontal`) axis
+    pub fn with_lens_tilt(mut self, vertical: f32, horizontal: f32) -> Self {
+        self.focal_plane.tilt(vertical, horizontal, &self.camera_vectors);
+        self
+    }
+
+    /// Scale radiance by the photometric exposure of the shutter speed, ISO and f-number.
+    /// Only meaningful for scenes lit in physical units, like the physical sky, arbitrary radiance renders near black
+    pub fn with_physical_exposure(mut self, settings: &PhysicalCameraSettings) -> Self {
+        self.exposure = settings.exposure();
+        self
+    }
+}
+
+impl CameraBuilder for PerspectiveCamera {
+    fn controls_mut(&mut self) -> &mut CameraControls {
+        &mut self.controls
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<CameraRay> {
+        let pixel_sample = self.view_plane.get_film_position(sample.film_x, sample.film_y);
+        let focus_point = self.focal_plane.focus_point(&self.position, &pixel_sample);
+
+        let ray_origin = self.get_ray_origin();
+        let ray_direction = focus_point - ray_origin;
+        let ray_direction = ray_direction.normalize();
+
+        Some(CameraRay {
+            ray: Ray::with_time(ray_origin, ray_direction, sample.time),
+            weight: self.exposure,
+        })
+    }
+
+    fn scene_depth_interval(&self) -> &Interval {
+        &self.scene_depth
+    }
+
+    fn shutter_interval(&self) -> Interval {
+        self.controls.shutter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::SensorSize;
+
+    #[test]
+    fn test_rays_share_origin() {
+        let camera = PerspectiveCamera::new(
+            Vec3::new(0.0, 0.0, 5.0), CameraOrientation::look_at(Vec3::zeros()),
+            60.0, FocusMode::AutoFocus, 0.0,
+            Interval::new(0.001, 100.0), &Resolution::new(200, 100)
+        ).unwrap();
+
+        let top_left = camera.generate_ray(&CameraSample { film_x: 0.0, film_y: 0.0, time: 0.0 }).unwrap().ray;
+        let bottom_right = camera.generate_ray(&CameraSample { film_x: 200.0, film_y: 100.0, time: 0.0 }).unwrap().ray;
+
+        assert_eq!(top_left.origin(), bottom_right.origin());
+        assert!((top_left.direction() - bottom_right.direction()).magnitude() > 0.1);
+    }
+
+    #[test]
+    fn test_lens_shift_keeps_orientation() {
+        let camera = PerspectiveCamera::new(
+            Vec3::new(0.0, 0.0, 5.0), CameraOrientation::look_at(Vec3::zeros()),
+            60.0, FocusMode::AutoFocus, 0.0,
+            Interval::new(0.001, 100.0), &Resolution::new(200, 100)
+        ).unwrap();
+        let shifted = camera.clone().with_lens_shift(0.0, 0.5);
+
+        let center = CameraSample { film_x: 100.0, film_y: 50.0, time: 0.0 };
+        let bottom = CameraSample { film_x: 100.0, film_y: 100.0, time: 0.0 };
+
+        // Shifting up by half a frame moves the old frame center to the bottom edge
+        let expected = *camera.generate_ray(&center).unwrap().ray.direction();
+        let actual = *shifted.generate_ray(&bottom).unwrap().ray.direction();
+        assert!((expected - actual).magnitude() < 1e-5);
+        assert!(shifted.generate_ray(&center).unwrap().ray.direction().y > 0.0);
+    }
+
+    #[test]
+    fn test_physical_camera_keeps_scene_brightness() {
+        let settings = PhysicalCameraSettings {
+            sensor: SensorSize::FULL_FRAME,
+            focal_length: 50.0,
+            f_number: 2.0,
+            shutter_speed: 1.0 / 125.0,
+            iso: 100.0,
+        };
+        let camera = || PerspectiveCamera::physical(
+            Vec3::new(0.0, 0.0, 5.0), CameraOrientation::look_at(Vec3::zeros()),
+            &settings, FocusMode::AutoFocus, Interval::new(0.001, 100.0), &Resolution::new(200, 100)
+        ).unwrap();
+        let sample = CameraSample { film_x: 100.0, film_y: 50.0, time: 0.0 };
+
+        // Scene radiance in arbitrary units, like the default sky gradient, passes through unscaled unless physical exposure is asked for
+        assert_eq!(camera().generate_ray(&sample).unwrap().weight, 1.0);
+
+        let exposed = camera().with_physical_exposure(&settings).generate_ray(&sample).unwrap().weight;
+        assert!(f32::abs(exposed - settings.exposure()) < 1e-9);
+    }
+}
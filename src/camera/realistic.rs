@@ -0,0 +1,500 @@
+use std::path::Path;
+use rand::{thread_rng, Rng};
+use nalgebra_glm::{Vec2, Vec3};
+
+use super::{Camera, CameraBuilder, CameraControls, CameraOrientation, CameraError, CameraSample, CameraRay, CameraVectors, FocusMode, SensorSize};
+use crate::resolution::Resolution;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+const MILLIMETERS_TO_METERS: f32 = 0.001;
+const EXIT_PUPIL_BINS: usize = 32;    // Radial bins from the sensor center to its corner
+const EXIT_PUPIL_GRID: usize = 32;    // Grid resolution over the rear element when searching for the pupil
+
+/// One spherical interface of a lens, all lengths in millimeters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensElement {
+    pub curvature_radius: f32,  // Zero marks the aperture stop, positive radii curve towards the sensor
+    pub thickness: f32,         // Distance along the axis to the next interface
+    pub ior: f32,               // Index of refraction behind the interface, zero or one is air
+    pub aperture: f32,          // Clear aperture diameter
+}
+
+/// Lens elements ordered from the front of the lens to the sensor
+#[derive(Debug, Clone, PartialEq)]
+pub struct LensPrescription {
+    elements: Vec<LensElement>,
+}
+
+#[derive(Debug)]
+pub enum LensPrescriptionError {
+    Io(std::io::Error),
+    InvalidLine(usize),
+    NoElements,
+}
+
+/// Camera tracing rays from the sensor through a thick multi-element lens.
+/// Vignetting, distortion and depth of field all follow from the lens prescription
+#[derive(Debug, Clone)]
+pub struct RealisticCamera {
+    position: Vec3,
+    scene_depth: Interval,
+    controls: CameraControls,
+    camera_vectors: CameraVectors,
+    elements: Vec<LensElement>,
+    sensor: SensorSize,
+    resolution: Resolution,
+    center_transmission: f32,
+    exit_pupils: Vec<Option<PupilBounds>>,
+}
+
+/// Bounds on the rear element, in units of its radius, of the rays that make it through the lens from a sensor
+/// point on the +x axis. `None` when no ray gets through
+#[derive(Debug, Clone, Copy)]
+struct PupilBounds {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl LensElement {
+    pub fn is_aperture_stop(&self) -> bool {
+        self.curvature_radius == 0.0
+    }
+
+    fn medium_ior(&self) -> f32 {
+        if self.ior <= 0.0 { 1.0 } else { self.ior }
+    }
+}
+
+impl LensPrescription {
+    pub fn new(elements: Vec<LensElement>) -> Result<Self, LensPrescriptionError> {
+        if elements.is_empty() {
+            return Err(LensPrescriptionError::NoElements)
+        }
+
+        Ok(LensPrescription { elements })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, LensPrescriptionError> {
+        let contents = std::fs::read_to_string(path).map_err(LensPrescriptionError::Io)?;
+        Self::parse(&contents)
+    }
+
+    /// Parse a prescription with one `radius thickness ior aperture` element per line, `#` starts a comment
+    pub fn parse(contents: &str) -> Result<Self, LensPrescriptionError> {
+        let mut elements = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let values: Vec<f32> = line.split_whitespace()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| LensPrescriptionError::InvalidLine(index + 1))?;
+
+            if values.len() != 4 || values.iter().any(|value| !value.is_finite()) {
+                return Err(LensPrescriptionError::InvalidLine(index + 1))
+            }
+
+            elements.push(LensElement {
+                curvature_radius: values[0],
+                thickness: values[1],
+                ior: values[2],
+                aperture: values[3],
+            });
+        }
+
+        Self::new(elements)
+    }
+
+    pub fn elements(&self) -> &[LensElement] {
+        &self.elements
+    }
+}
+
+impl std::fmt::Display for LensPrescriptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LensPrescriptionError::Io(error) => write!(f, "failed to read lens prescription: {}", error),
+            LensPrescriptionError::InvalidLine(line) => write!(f, "line {} of the lens prescription is not `radius thickness ior aperture`", line),
+            LensPrescriptionError::NoElements => write!(f, "lens prescription has no elements"),
+        }
+    }
+}
+
+impl std::error::Error for LensPrescriptionError {}
+
+impl RealisticCamera {
+    /// The lens is focused by moving the sensor, the focus distance is measured from the sensor.
+    /// The sensor width is fitted to the image width
+    pub fn new(position: Vec3, orientation: CameraOrientation, prescription: &LensPrescription, sensor: SensorSize, focus_mode: FocusMode, scene_depth: Interval, resolution: &Resolution) -> Result<Self, CameraError> {
+        let focus_distance = focus_mode.focal_length(&position, &orientation)? / MILLIMETERS_TO_METERS;
+        let camera_vectors = CameraVectors::new(&position, &orientation)?;
+
+        let mut camera = RealisticCamera {
+            position,
+            scene_depth,
+            controls: CameraControls::default(),
+            camera_vectors,
+            elements: prescription.elements().to_vec(),
+            sensor: SensorSize { width: sensor.width, height: sensor.width / resolution.aspect_ratio() },
+            resolution: *resolution,
+            center_transmission: 1.0,
+            exit_pupils: Vec::new(),
+        };
+
+        camera.focus(focus_distance)?;
+        camera.center_transmission = camera.estimate_center_transmission();
+        camera.exit_pupils = camera.compute_exit_pupils();
+
+        Ok(camera)
+    }
+
+    fn focus(&mut self, focus_distance: f32) -> Result<(), CameraError> {
+        // A paraxial ray from the focus point crosses the axis where the sensor has to be,
+        // moving the sensor moves the object distance as well so repeat until it settles
+        for _ in 0..8 {
+            let object = Vec3::new(0.0, 0.0, -focus_distance);
+            let height = 0.01 * self.rear_element().aperture;
+            let front_z = self.element_z(0);
+
+            let (origin, direction) = self.trace_from_scene(&object, &(Vec3::new(height, 0.0, front_z) - object))
+                .ok_or(CameraError::UnfocusableLens)?;
+
+            if direction.x >= 0.0 {
+                return Err(CameraError::UnfocusableLens)
+            }
+
+            let axis_crossing = origin.z - origin.x * direction.z / direction.x;
+            let sensor_distance = self.rear_element().thickness + axis_crossing;
+            if sensor_distance <= 0.0 || !sensor_distance.is_finite() {
+                return Err(CameraError::UnfocusableLens)
+            }
+
+            let last = self.elements.len() - 1;
+            self.elements[last].thickness = sensor_distance;
+        }
+
+        Ok(())
+    }
+
+    fn estimate_center_transmission(&self) -> f32 {
+        // Fraction of the rear element that sees through the lens from the sensor center, used to normalize exposure
+        let steps = 32;
+        let (mut total, mut passed) = (0, 0);
+
+        for i in 0..steps {
+            for j in 0..steps {
+                let x = 2.0 * (i as f32 + 0.5) / steps as f32 - 1.0;
+                let y = 2.0 * (j as f32 + 0.5) / steps as f32 - 1.0;
+                if x * x + y * y >= 1.0 {
+                    continue;
+                }
+
+                total += 1;
+                if self.trace_from_sensor(&Vec3::zeros(), x, y).is_some() {
+                    passed += 1;
+                }
+            }
+        }
+
+        f32::max(passed as f32 / total as f32, 1e-3)
+    }
+
+    /// Find which part of the rear element sees through the lens at increasing distances from the sensor center.
+    /// At small apertures only a sliver of the rear element passes light, sampling just that part saves the rays
+    /// the aperture stop would block
+    fn compute_exit_pupils(&self) -> Vec<Option<PupilBounds>> {
+        let max_radius = self.sensor_radius();
+        let cell = 2.0 / EXIT_PUPIL_GRID as f32;
+
+        (0..EXIT_PUPIL_BINS).map(|bin| {
+            let mut bounds: Option<PupilBounds> = None;
+
+            for edge in [bin, bin + 1] {
+                let sensor_point = Vec3::new(edge as f32 / EXIT_PUPIL_BINS as f32 * max_radius, 0.0, 0.0);
+
+                for i in 0..EXIT_PUPIL_GRID {
+                    for j in 0..EXIT_PUPIL_GRID {
+                        let point = Vec2::new(-1.0 + (i as f32 + 0.5) * cell, -1.0 + (j as f32 + 0.5) * cell);
+                        if self.trace_from_sensor(&sensor_point, point.x, point.y).is_none() {
+                            continue;
+                        }
+
+                        bounds = Some(match bounds {
+                            Some(bounds) => PupilBounds { min: bounds.min.inf(&point), max: bounds.max.sup(&point) },
+                            None => PupilBounds { min: point, max: point },
+                        });
+                    }
+                }
+            }
+
+            // The grid only samples cell centers, grow by a cell so rays between them are not cut off
+            bounds.map(|bounds| PupilBounds {
+                min: bounds.min.add_scalar(-cell).sup(&Vec2::new(-1.0, -1.0)),
+                max: bounds.max.add_scalar(cell).inf(&Vec2::new(1.0, 1.0)),
+            })
+        }).collect()
+    }
+
+    /// Sample a point on the rear element within the exit pupil seen from `sensor_point`, in units of the rear
+    /// element's radius. Returns the point and its area relative to sampling the whole rear element
+    fn sample_exit_pupil(&self, sensor_point: &Vec3) -> Option<(f32, f32, f32)> {
+        let radius = f32::hypot(sensor_point.x, sensor_point.y);
+        let bounds = self.exit_pupil(radius)?;
+
+        let mut rng = thread_rng();
+        let (u, v) = (rng.gen_range(bounds.min.x..=bounds.max.x), rng.gen_range(bounds.min.y..=bounds.max.y));
+
+        // Bounds were found on the +x axis, rotate them around to the sensor point
+        let (sin_phi, cos_phi) = if radius > 0.0 { (sensor_point.y / radius, sensor_point.x / radius) } else { (0.0, 1.0) };
+        let (x, y) = (u * cos_phi - v * sin_phi, u * sin_phi + v * cos_phi);
+
+        let area = (bounds.max.x - bounds.min.x) * (bounds.max.y - bounds.min.y);
+        Some((x, y, area / std::f32::consts::PI))
+    }
+
+    fn exit_pupil(&self, sensor_radius: f32) -> Option<PupilBounds> {
+        let bin = (sensor_radius / self.sensor_radius() * EXIT_PUPIL_BINS as f32) as usize;
+        self.exit_pupils[usize::min(bin, EXIT_PUPIL_BINS - 1)]
+    }
+
+    /// Distance from the sensor center to its corners
+    fn sensor_radius(&self) -> f32 {
+        f32::hypot(self.sensor.width, self.sensor.height) / 2.0
+    }
+
+    fn rear_element(&self) -> &LensElement {
+        self.elements.last().unwrap()
+    }
+
+    /// Axial position of an element's vertex, with the sensor at z = 0 and the scene towards -z
+    fn element_z(&self, index: usize) -> f32 {
+        -self.elements[index..].iter().map(|element| element.thickness).sum::<f32>()
+    }
+
+    fn trace_from_sensor(&self, sensor_point: &Vec3, x: f32, y: f32) -> Option<(Vec3, Vec3)> {
+        let rear_index = self.elements.len() - 1;
+        let radius = self.rear_element().aperture / 2.0;
+        let rear_point = Vec3::new(x * radius, y * radius, self.element_z(rear_index));
+
+        let mut origin = *sensor_point;
+        let mut direction = (rear_point - sensor_point).normalize();
+
+        for index in (0..self.elements.len()).rev() {
+            let element = &self.elements[index];
+            let (hit, normal) = self.intersect_element(index, &origin, &direction)?;
+
+            let outside_ior = if index > 0 { self.elements[index - 1].medium_ior() } else { 1.0 };
+            if !element.is_aperture_stop() {
+                direction = refract(&direction, &normal, element.medium_ior() / outside_ior)?;
+            }
+
+            origin = hit;
+        }
+
+        Some((origin, direction))
+    }
+
+    fn trace_from_scene(&self, origin: &Vec3, direction: &Vec3) -> Option<(Vec3, Vec3)> {
+        let mut origin = *origin;
+        let mut direction = direction.normalize();
+
+        for index in 0..self.elements.len() {
+            let element = &self.elements[index];
+            let (hit, normal) = self.intersect_element(index, &origin, &direction)?;
+
+            let outside_ior = if index > 0 { self.elements[index - 1].medium_ior() } else { 1.0 };
+            if !element.is_aperture_stop() {
+                direction = refract(&direction, &normal, outside_ior / element.medium_ior())?;
+            }
+
+            origin = hit;
+        }
+
+        Some((origin, direction))
+    }
+
+    /// Hit point and normal facing the incoming ray, `None` if the ray misses or is blocked by the element's rim
+    fn intersect_element(&self, index: usize, origin: &Vec3, direction: &Vec3) -> Option<(Vec3, Vec3)> {
+        let element = &self.elements[index];
+        let vertex_z = self.element_z(index);
+
+        let (t, normal) = if element.is_aperture_stop() {
+            let t = (vertex_z - origin.z) / direction.z;
+            (t, Vec3::new(0.0, 0.0, -direction.z.signum()))
+        }
+        else {
+            let center = Vec3::new(0.0, 0.0, vertex_z + element.curvature_radius);
+            let oc = origin - center;
+
+            let b = oc.dot(direction);
+            let c = oc.magnitude_squared() - element.curvature_radius * element.curvature_radius;
+            let discriminant = b * b - c;
+            if discriminant < 0.0 {
+                return None
+            }
+
+            // The vertex side of the sphere is the closer hit when travelling towards its center
+            let root = f32::sqrt(discriminant);
+            let use_closer = (direction.z > 0.0) != (element.curvature_radius < 0.0);
+            let t = if use_closer { -b - root } else { -b + root };
+
+            let normal = (origin + t * direction - center).normalize();
+            let normal = if normal.dot(direction) > 0.0 { -normal } else { normal };
+            (t, normal)
+        };
+
+        if t <= 0.0 || !t.is_finite() {
+            return None
+        }
+
+        let hit = origin + t * direction;
+        if hit.x * hit.x + hit.y * hit.y > element.aperture * element.aperture / 4.0 {
+            return None
+        }
+
+        Some((hit, normal))
+    }
+}
+
+impl CameraBuilder for RealisticCamera {
+    fn controls_mut(&mut self) -> &mut CameraControls {
+        &mut self.controls
+    }
+}
+
+impl Camera for RealisticCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<CameraRay> {
+        // The lens flips the image, so the top left of the image lands on the bottom right of the sensor
+        let sensor_point = Vec3::new(
+            (0.5 - sample.film_x / self.resolution.width() as f32) * self.sensor.width,
+            (sample.film_y / self.resolution.height() as f32 - 0.5) * self.sensor.height,
+            0.0,
+        );
+
+        let (x, y, pupil_area) = self.sample_exit_pupil(&sensor_point)?;
+        let (origin, direction) = self.trace_from_sensor(&sensor_point, x, y)?;
+
+        // Rays leaving the sensor at an angle carry less light, cos^4 falloff. The pupil area keeps the weight
+        // matching uniform sampling of the whole rear element
+        let rear_point = Vec3::new(x, y, 0.0) * (self.rear_element().aperture / 2.0)
+            + Vec3::new(0.0, 0.0, self.element_z(self.elements.len() - 1));
+        let cos_theta = (rear_point - sensor_point).normalize().z.abs();
+        let weight = cos_theta * cos_theta * cos_theta * cos_theta * pupil_area / self.center_transmission;
+
+        let world_origin = self.position + self.camera_vectors.local_to_world(&(origin * MILLIMETERS_TO_METERS));
+        let world_direction = self.camera_vectors.local_to_world(&direction).normalize();
+
+        Some(CameraRay {
+            ray: Ray::with_time(world_origin, world_direction, sample.time),
+            weight,
+        })
+    }
+
+    fn scene_depth_interval(&self) -> &Interval {
+        &self.scene_depth
+    }
+
+    fn shutter_interval(&self) -> Interval {
+        self.controls.shutter()
+    }
+}
+
+fn refract(incoming: &Vec3, normal: &Vec3, ior_fraction: f32) -> Option<Vec3> {
+    let cos_theta = f32::min(-incoming.dot(normal), 1.0);
+    let sin_squared = ior_fraction * ior_fraction * (1.0 - cos_theta * cos_theta);
+
+    // Total internal reflection, the ray never leaves the glass
+    if sin_squared > 1.0 {
+        return None
+    }
+
+    Some(ior_fraction * incoming + (ior_fraction * cos_theta - f32::sqrt(1.0 - sin_squared)) * normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOUBLE_GAUSS: &str = include_str!("../../lenses/double_gauss_50mm.dat");
+
+    fn double_gauss_camera() -> RealisticCamera {
+        let prescription = LensPrescription::parse(DOUBLE_GAUSS).unwrap();
+
+        RealisticCamera::new(
+            Vec3::new(0.0, 0.0, 5.0), CameraOrientation::look_at(Vec3::zeros()),
+            &prescription, SensorSize::FULL_FRAME, FocusMode::AutoFocus,
+            Interval::new(0.001, 100.0), &Resolution::new(300, 200)
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_parse_prescription() {
+        let prescription = LensPrescription::parse("# radius thickness ior aperture\n29.475 3.76 1.67 25.2\n\n0 4.5 0 17.1 # stop\n").unwrap();
+
+        assert_eq!(prescription.elements().len(), 2);
+        assert!(prescription.elements()[1].is_aperture_stop());
+
+        assert!(matches!(LensPrescription::parse("1.0 2.0 1.5"), Err(LensPrescriptionError::InvalidLine(1))));
+        assert!(matches!(LensPrescription::parse("# empty"), Err(LensPrescriptionError::NoElements)));
+    }
+
+    #[test]
+    fn test_center_rays_converge_on_focus_point() {
+        let camera = double_gauss_camera();
+        let sample = CameraSample { film_x: 150.0, film_y: 100.0, time: 0.0 };
+
+        let mut traced = 0;
+        for _ in 0..200 {
+            if let Some(camera_ray) = camera.generate_ray(&sample) {
+                let ray = camera_ray.ray;
+                let t = (Vec3::zeros() - ray.origin()).dot(ray.direction());
+                let closest = ray.origin() + t * ray.direction();
+
+                assert!(closest.magnitude() < 0.05, "ray misses the focus point by {}", closest.magnitude());
+                traced += 1;
+            }
+        }
+
+        assert!(traced > 0);
+    }
+
+    #[test]
+    fn test_corners_are_vignetted() {
+        let camera = double_gauss_camera();
+
+        let transmitted = |film_x: f32, film_y: f32| -> f32 {
+            (0..2000)
+                .filter_map(|_| camera.generate_ray(&CameraSample { film_x, film_y, time: 0.0 }))
+                .map(|camera_ray| camera_ray.weight)
+                .sum()
+        };
+
+        assert!(transmitted(0.0, 0.0) < 0.8 * transmitted(150.0, 100.0));
+    }
+
+    #[test]
+    fn test_exit_pupil_holds_passing_rays() {
+        let camera = double_gauss_camera();
+        let sensor_point = Vec3::new(0.0, 0.3 * camera.sensor.height, 0.0);
+
+        // Every ray through the lens starts inside the pupil bounds, rotated a quarter turn up to the sensor point
+        let bounds = camera.exit_pupil(sensor_point.y).unwrap();
+        let mut rng = thread_rng();
+
+        for _ in 0..2000 {
+            let (x, y): (f32, f32) = (rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            if camera.trace_from_sensor(&sensor_point, x, y).is_some() {
+                assert!(bounds.min.x <= y && y <= bounds.max.x && bounds.min.y <= -x && -x <= bounds.max.y);
+            }
+        }
+
+        // And the pupil is smaller than the rear element, so fewer rays are wasted on the aperture stop
+        let (_, _, pupil_area) = camera.sample_exit_pupil(&sensor_point).unwrap();
+        assert!(pupil_area < 1.0);
+    }
+}
@@ -0,0 +1,296 @@
+use nalgebra_glm::Vec3;
+
+use super::perspective::PerspectiveCamera;
+use super::panoramic::{PanoramicCamera, PanoramicProjection};
+use super::{Camera, CameraOrientation, CameraError, CameraSample, CameraRay, CameraVectors, FocusMode};
+use crate::resolution::Resolution;
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::tile::Tile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoEye {
+    Left,
+    Right,
+}
+
+/// How both eyes are packed into the rendered image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    SideBySide,     // Left eye on the left half
+    TopBottom,      // Left eye on the top half
+    SeparateFiles,  // Rendered side by side, saved as one file per eye
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum StereoConvergence {
+    OffAxis { distance: f32 },  // Parallel eyes with shifted frustums, zero parallax at `distance`
+    ToeIn { distance: f32 },    // Both eyes rotated to look at the point `distance` ahead
+}
+
+/// Renders a left and right eye into one image, laid out by `layout`
+pub struct StereoCamera {
+    left: Box<dyn Camera + Sync>,
+    right: Box<dyn Camera + Sync>,
+    layout: StereoLayout,
+    eye_resolution: Resolution,
+}
+
+/// Omni-directional stereo panorama, every column is seen from the point on the interocular circle that faces it
+#[derive(Debug, Clone)]
+struct OmniDirectionalEye {
+    panorama: PanoramicCamera,
+    position: Vec3,
+    up: Vec3,
+    offset: f32,    // Signed distance from the center, negative for the left eye
+}
+
+impl StereoEye {
+    fn sign(&self) -> f32 {
+        match self {
+            StereoEye::Left => -1.0,
+            StereoEye::Right => 1.0,
+        }
+    }
+}
+
+impl StereoLayout {
+    /// Resolution of the packed image holding both eyes
+    pub fn film_resolution(&self, eye_resolution: &Resolution) -> Resolution {
+        let (width, height) = eye_resolution.dimensions();
+
+        match self {
+            StereoLayout::SideBySide | StereoLayout::SeparateFiles => Resolution::new(2 * width, height),
+            StereoLayout::TopBottom => Resolution::new(width, 2 * height),
+        }
+    }
+
+    /// Pixel bounds of one eye in the packed image
+    pub fn eye_bounds(&self, eye: StereoEye, eye_resolution: &Resolution) -> Tile {
+        let (width, height) = eye_resolution.dimensions();
+        let index = match eye {
+            StereoEye::Left => 0,
+            StereoEye::Right => 1,
+        };
+
+        match self {
+            StereoLayout::SideBySide | StereoLayout::SeparateFiles => Tile { x: index * width, y: 0, width, height },
+            StereoLayout::TopBottom => Tile { x: 0, y: index * height, width, height },
+        }
+    }
+}
+
+impl StereoCamera {
+    /// Pair of perspective cameras `interocular_distance` apart, centered on `position`
+    #[allow(clippy::too_many_arguments)]
+    pub fn perspective(position: Vec3, orientation: CameraOrientation, vertical_fov: f32, interocular_distance: f32, convergence: StereoConvergence, layout: StereoLayout, scene_depth: Interval, eye_resolution: &Resolution) -> Result<Self, CameraError> {
+        let camera_vectors = CameraVectors::new(&position, &orientation)?;
+
+        let eye_camera = |eye: StereoEye| -> Result<Box<dyn Camera + Sync>, CameraError> {
+            let eye_position = position + eye.sign() * interocular_distance / 2.0 * camera_vectors.right();
+
+            let camera = match convergence {
+                StereoConvergence::OffAxis { distance } => {
+                    let orientation = CameraOrientation::LookAt {
+                        target: eye_position + distance * camera_vectors.forward(),
+                        up: camera_vectors.up(),
+                        roll: 0.0,
+                    };
+
+                    // Shift each frustum towards the center so both frame the same window at the convergence distance
+                    let view_width = 2.0 * distance * f32::tan(f32::to_radians(vertical_fov) / 2.0) * eye_resolution.aspect_ratio();
+                    let shift = -eye.sign() * interocular_distance / 2.0 / view_width;
+
+                    PerspectiveCamera::new(eye_position, orientation, vertical_fov, FocusMode::AutoFocus, 0.0, scene_depth, eye_resolution)?
+                        .with_lens_shift(shift, 0.0)
+                },
+                StereoConvergence::ToeIn { distance } => {
+                    let orientation = CameraOrientation::LookAt {
+                        target: position + distance * camera_vectors.forward(),
+                        up: camera_vectors.up(),
+                        roll: 0.0,
+                    };
+
+                    PerspectiveCamera::new(eye_position, orientation, vertical_fov, FocusMode::AutoFocus, 0.0, scene_depth, eye_resolution)?
+                },
+            };
+
+            Ok(Box::new(camera))
+        };
+
+        Ok(StereoCamera {
+            left: eye_camera(StereoEye::Left)?,
+            right: eye_camera(StereoEye::Right)?,
+            layout,
+            eye_resolution: *eye_resolution,
+        })
+    }
+
+    /// Omni-directional stereo equirectangular panorama for VR, use a 2:1 eye resolution
+    pub fn omnidirectional(position: Vec3, orientation: CameraOrientation, interocular_distance: f32, layout: StereoLayout, scene_depth: Interval, eye_resolution: &Resolution) -> Result<Self, CameraError> {
+        let panorama = PanoramicCamera::new(position, orientation, PanoramicProjection::Equirectangular, scene_depth, eye_resolution)?;
+        let up = CameraVectors::new(&position, &orientation)?.up();
+
+        let eye_camera = |eye: StereoEye| -> Box<dyn Camera + Sync> {
+            Box::new(OmniDirectionalEye {
+                panorama: panorama.clone(),
+                position,
+                up,
+                offset: eye.sign() * interocular_distance / 2.0,
+            })
+        };
+
+        Ok(StereoCamera {
+            left: eye_camera(StereoEye::Left),
+            right: eye_camera(StereoEye::Right),
+            layout,
+            eye_resolution: *eye_resolution,
+        })
+    }
+
+    pub fn layout(&self) -> StereoLayout {
+        self.layout
+    }
+
+    /// Resolution to configure the renderer with
+    pub fn film_resolution(&self) -> Resolution {
+        self.layout.film_resolution(&self.eye_resolution)
+    }
+
+    fn eye_camera(&self, eye: StereoEye) -> &(dyn Camera + Sync) {
+        match eye {
+            StereoEye::Left => self.left.as_ref(),
+            StereoEye::Right => self.right.as_ref(),
+        }
+    }
+}
+
+impl Camera for StereoCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<CameraRay> {
+        let right_bounds = self.layout.eye_bounds(StereoEye::Right, &self.eye_resolution);
+        let eye = if sample.film_x >= right_bounds.x as f32 && sample.film_y >= right_bounds.y as f32 {
+            StereoEye::Right
+        }
+        else {
+            StereoEye::Left
+        };
+
+        let bounds = self.layout.eye_bounds(eye, &self.eye_resolution);
+        let eye_sample = CameraSample {
+            film_x: sample.film_x - bounds.x as f32,
+            film_y: sample.film_y - bounds.y as f32,
+            time: sample.time,
+        };
+
+        self.eye_camera(eye).generate_ray(&eye_sample)
+    }
+
+    fn scene_depth_interval(&self) -> &Interval {
+        self.left.scene_depth_interval()
+    }
+
+    fn shutter_interval(&self) -> Interval {
+        self.left.shutter_interval()
+    }
+
+    fn film_regions(&self) -> Vec<Tile> {
+        vec![
+            self.layout.eye_bounds(StereoEye::Left, &self.eye_resolution),
+            self.layout.eye_bounds(StereoEye::Right, &self.eye_resolution),
+        ]
+    }
+}
+
+impl Camera for OmniDirectionalEye {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<CameraRay> {
+        let camera_ray = self.panorama.generate_ray(sample)?;
+        let direction = *camera_ray.ray.direction();
+
+        // Offset along the horizontal tangent of the viewing circle, scaled by the cosine of the elevation so
+        // both eyes meet at the poles instead of swirling around them
+        let tangent = direction.cross(&self.up);
+        let tangent_length = tangent.magnitude();
+        let origin = if tangent_length > 1e-6 {
+            let pole_falloff = f32::sqrt(f32::max(1.0 - direction.dot(&self.up).powi(2), 0.0));
+            self.position + (self.offset * pole_falloff / tangent_length) * tangent
+        }
+        else {
+            self.position
+        };
+
+        Some(CameraRay {
+            ray: Ray::with_time(origin, direction, sample.time),
+            weight: camera_ray.weight,
+        })
+    }
+
+    fn scene_depth_interval(&self) -> &Interval {
+        self.panorama.scene_depth_interval()
+    }
+
+    fn shutter_interval(&self) -> Interval {
+        self.panorama.shutter_interval()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(film_x: f32, film_y: f32) -> CameraSample {
+        CameraSample { film_x, film_y, time: 0.0 }
+    }
+
+    #[test]
+    fn test_layout_bounds() {
+        let eye_resolution = Resolution::new(100, 50);
+
+        let side_by_side = StereoLayout::SideBySide.eye_bounds(StereoEye::Right, &eye_resolution);
+        assert_eq!((side_by_side.x, side_by_side.y), (100, 0));
+        assert_eq!(StereoLayout::TopBottom.film_resolution(&eye_resolution).dimensions(), (100, 100));
+    }
+
+    #[test]
+    fn test_off_axis_eyes_converge() {
+        let camera = StereoCamera::perspective(
+            Vec3::new(0.0, 0.0, 5.0), CameraOrientation::look_at(Vec3::zeros()),
+            60.0, 0.065, StereoConvergence::OffAxis { distance: 5.0 }, StereoLayout::SideBySide,
+            Interval::new(0.001, 100.0), &Resolution::new(100, 50)
+        ).unwrap();
+
+        let left = camera.generate_ray(&sample(50.0, 25.0)).unwrap().ray;
+        let right = camera.generate_ray(&sample(150.0, 25.0)).unwrap().ray;
+
+        assert!(left.origin().x < 0.0 && right.origin().x > 0.0);
+
+        // Both eye centers look at the convergence point
+        for ray in [left, right] {
+            let t = (Vec3::zeros() - ray.origin()).dot(ray.direction());
+            assert!((ray.origin() + t * ray.direction()).magnitude() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_omnidirectional_eyes_offset_sideways() {
+        let camera = StereoCamera::omnidirectional(
+            Vec3::zeros(), CameraOrientation::YawPitchRoll { yaw: 0.0, pitch: 0.0, roll: 0.0 },
+            0.065, StereoLayout::TopBottom, Interval::new(0.001, 100.0), &Resolution::new(200, 100)
+        ).unwrap();
+
+        // Center of the panorama looks down -z, the left eye sits to the left
+        let left = camera.generate_ray(&sample(100.0, 50.0)).unwrap().ray;
+        let right = camera.generate_ray(&sample(100.0, 150.0)).unwrap().ray;
+
+        assert!(f32::abs(left.origin().x + 0.0325) < 1e-4);
+        assert!(f32::abs(right.origin().x - 0.0325) < 1e-4);
+        assert!((left.direction() - right.direction()).magnitude() < 1e-5);
+
+        // Straight up both eyes share the center, halfway up the offset has shrunk by the cosine of the elevation
+        let top = camera.generate_ray(&sample(100.0, 0.0)).unwrap().ray;
+        assert!(top.origin().magnitude() < 1e-3);
+
+        let halfway = camera.generate_ray(&sample(100.0, 25.0)).unwrap().ray;
+        let elevation = f32::asin(halfway.direction().normalize().y);
+        assert!(f32::abs(halfway.origin().magnitude() - 0.0325 * f32::cos(elevation)) < 1e-4);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::resolution::Resolution;
+use crate::tile::Tile;
+
+#[derive(Debug, Clone, Copy)]
+pub enum CropWindow {
+    Pixels { x: u32, y: u32, width: u32, height: u32 },
+    Normalized { min_x: f32, min_y: f32, max_x: f32, max_y: f32 },   // [0, 1] over the full frame, origin top left
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CropOutput {
+    CropOnly,
+    FullFrame,  // Pixels outside the crop window are left black
+}
+
+impl CropWindow {
+    /// Pixel region covered by the crop window, clamped to the image
+    pub fn pixel_bounds(&self, resolution: &Resolution) -> Tile {
+        let (width, height) = resolution.dimensions();
+
+        let (min_x, min_y, max_x, max_y) = match *self {
+            CropWindow::Pixels { x, y, width, height } => (x, y, x.saturating_add(width), y.saturating_add(height)),
+            CropWindow::Normalized { min_x, min_y, max_x, max_y } => (
+                f32::floor(min_x * width as f32) as u32,
+                f32::floor(min_y * height as f32) as u32,
+                f32::ceil(max_x * width as f32) as u32,
+                f32::ceil(max_y * height as f32) as u32,
+            ),
+        };
+
+        let min_x = u32::min(min_x, width);
+        let min_y = u32::min(min_y, height);
+        let max_x = u32::clamp(max_x, min_x, width);
+        let max_y = u32::clamp(max_y, min_y, height);
+
+        Tile {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x,
+            height: max_y - min_y,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_bounds() {
+        let crop = CropWindow::Pixels { x: 10, y: 20, width: 30, height: 40 };
+
+        assert_eq!(crop.pixel_bounds(&Resolution::new(100, 100)), Tile { x: 10, y: 20, width: 30, height: 40 });
+        assert_eq!(crop.pixel_bounds(&Resolution::new(25, 50)), Tile { x: 10, y: 20, width: 15, height: 30 });
+    }
+
+    #[test]
+    fn test_normalized_bounds() {
+        let crop = CropWindow::Normalized { min_x: 0.25, min_y: 0.5, max_x: 0.75, max_y: 1.5 };
+
+        assert_eq!(crop.pixel_bounds(&Resolution::new(1920, 1080)), Tile { x: 480, y: 540, width: 960, height: 540 });
+    }
+}
@@ -0,0 +1,233 @@
+pub mod filter;
+
+use nalgebra_glm::Vec3;
+use image::{Rgb32FImage, Rgb};
+
+use crate::resolution::Resolution;
+use crate::tile::Tile;
+use filter::ReconstructionFilter;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FilmPixel {
+    weighted_color: Vec3,
+    weight: f32,
+}
+
+/// Accumulates radiance samples, splatting each sample into every pixel covered by the reconstruction filter.
+///
+/// Film coordinates are continuous, pixel (x, y) covers [x, x + 1) x [y, y + 1) with its center at (x + 0.5, y + 0.5).
+pub struct Film {
+    resolution: Resolution,
+    filter: ReconstructionFilter,
+    regions: Vec<Tile>,
+    pixels: Vec<FilmPixel>,
+}
+
+/// Thread local film for a single tile, extended by the filter radius so samples can splat across tile edges
+pub struct FilmTile {
+    min: (u32, u32),
+    max: (u32, u32),    // Exclusive
+    filter: ReconstructionFilter,
+    regions: Vec<Tile>,
+    pixels: Vec<FilmPixel>,
+}
+
+impl Film {
+    pub fn new(resolution: Resolution, filter: ReconstructionFilter) -> Self {
+        Film {
+            resolution,
+            filter,
+            regions: Vec::new(),
+            pixels: vec![FilmPixel::default(); (resolution.width() * resolution.height()) as usize],
+        }
+    }
+
+    /// Split the film into separate images, like the eyes of a stereo pair, so samples do not bleed between them
+    pub fn set_regions(&mut self, regions: Vec<Tile>) {
+        self.regions = regions;
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    pub fn add_sample(&mut self, film_x: f32, film_y: f32, color: Vec3) {
+        let (width, height) = self.resolution.dimensions();
+        splat(&mut self.pixels, (0, 0), (width, height), &self.filter, &self.regions, film_x, film_y, color);
+    }
+
+    pub fn create_tile(&self, tile: &Tile) -> FilmTile {
+        FilmTile::new(tile, &self.resolution, &self.filter, &self.regions)
+    }
+
+    pub fn merge_tile(&mut self, tile: FilmTile) {
+        let width = self.resolution.width();
+        let tile_width = tile.max.0 - tile.min.0;
+
+        for y in tile.min.1..tile.max.1 {
+            for x in tile.min.0..tile.max.0 {
+                let tile_pixel = tile.pixels[((y - tile.min.1) * tile_width + (x - tile.min.0)) as usize];
+                let pixel = &mut self.pixels[(y * width + x) as usize];
+
+                pixel.weighted_color += tile_pixel.weighted_color;
+                pixel.weight += tile_pixel.weight;
+            }
+        }
+    }
+
+    pub fn to_image(&self) -> Rgb32FImage {
+        let (width, height) = self.resolution.dimensions();
+
+        Rgb32FImage::from_fn(width, height, |x, y| {
+            let pixel = self.pixels[(y * width + x) as usize];
+
+            // Filters with negative lobes may cancel out entirely, avoid blowing up the pixel
+            if f32::abs(pixel.weight) < 1e-8 {
+                return Rgb([0.0, 0.0, 0.0])
+            }
+
+            let color = pixel.weighted_color / pixel.weight;
+            Rgb([color.x, color.y, color.z])
+        })
+    }
+}
+
+impl FilmTile {
+    /// Tile of a film with `resolution`, `filter` and `regions`, created without access to the film itself
+    pub fn new(tile: &Tile, resolution: &Resolution, filter: &ReconstructionFilter, regions: &[Tile]) -> Self {
+        let radius = filter.radius();
+        let (width, height) = resolution.dimensions();
+
+        // Any pixel whose center is within the filter radius of the tile may receive samples
+        let min_x = f32::max(f32::ceil(tile.x as f32 - 0.5 - radius), 0.0) as u32;
+        let min_y = f32::max(f32::ceil(tile.y as f32 - 0.5 - radius), 0.0) as u32;
+        let max_x = u32::min(f32::floor((tile.x + tile.width) as f32 - 0.5 + radius) as u32 + 1, width);
+        let max_y = u32::min(f32::floor((tile.y + tile.height) as f32 - 0.5 + radius) as u32 + 1, height);
+
+        FilmTile {
+            min: (min_x, min_y),
+            max: (max_x, max_y),
+            filter: *filter,
+            regions: regions.to_vec(),
+            pixels: vec![FilmPixel::default(); ((max_x - min_x) * (max_y - min_y)) as usize],
+        }
+    }
+
+    pub fn add_sample(&mut self, film_x: f32, film_y: f32, color: Vec3) {
+        splat(&mut self.pixels, self.min, self.max, &self.filter, &self.regions, film_x, film_y, color);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn splat(pixels: &mut [FilmPixel], min: (u32, u32), max: (u32, u32), filter: &ReconstructionFilter, regions: &[Tile], film_x: f32, film_y: f32, color: Vec3) {
+    let radius = filter.radius();
+
+    // Samples stay within the region they landed in
+    let (lower, upper) = match regions.iter().find(|region| region.contains(film_x, film_y)) {
+        Some(region) => (
+            (u32::max(min.0, region.x), u32::max(min.1, region.y)),
+            (u32::min(max.0, region.x + region.width), u32::min(max.1, region.y + region.height)),
+        ),
+        None => (min, max),
+    };
+
+    // Pixels whose center lies within the filter radius of the sample
+    let start_x = f32::max(f32::ceil(film_x - 0.5 - radius), lower.0 as f32);
+    let start_y = f32::max(f32::ceil(film_y - 0.5 - radius), lower.1 as f32);
+    let end_x = f32::min(f32::floor(film_x - 0.5 + radius), upper.0 as f32 - 1.0);
+    let end_y = f32::min(f32::floor(film_y - 0.5 + radius), upper.1 as f32 - 1.0);
+    if end_x < start_x || end_y < start_y {
+        return
+    }
+
+    let row_length = max.0 - min.0;
+    for y in (start_y as u32)..=(end_y as u32) {
+        for x in (start_x as u32)..=(end_x as u32) {
+            let weight = filter.evaluate(x as f32 + 0.5 - film_x, y as f32 + 0.5 - film_y);
+            if weight == 0.0 {
+                continue;
+            }
+
+            let pixel = &mut pixels[((y - min.1) * row_length + (x - min.0)) as usize];
+            pixel.weighted_color += weight * color;
+            pixel.weight += weight;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filter::FilterKind;
+
+    #[test]
+    fn test_box_stays_in_pixel() {
+        let mut film = Film::new(Resolution::new(3, 3), ReconstructionFilter::default());
+        film.add_sample(1.25, 1.75, Vec3::new(1.0, 1.0, 1.0));
+
+        let image = film.to_image();
+        assert_eq!(image.get_pixel(1, 1).0, [1.0, 1.0, 1.0]);
+        assert_eq!(image.get_pixel(0, 1).0, [0.0, 0.0, 0.0]);
+        assert_eq!(image.get_pixel(1, 2).0, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_splat_into_neighbours() {
+        let mut film = Film::new(Resolution::new(3, 3), ReconstructionFilter::new(FilterKind::Tent, 1.5));
+        film.add_sample(1.5, 1.5, Vec3::new(1.0, 0.5, 0.25));
+
+        let image = film.to_image();
+        for (_, _, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel.0, [1.0, 0.5, 0.25]);
+        }
+    }
+
+    #[test]
+    fn test_merge_tiles() {
+        let filter = ReconstructionFilter::new(FilterKind::Gaussian { alpha: 2.0 }, 2.0);
+        let mut reference = Film::new(Resolution::new(8, 8), filter);
+        let mut tiled = Film::new(Resolution::new(8, 8), filter);
+
+        let samples = [(3.9, 3.2, Vec3::new(1.0, 0.0, 0.0)), (4.1, 4.7, Vec3::new(0.0, 1.0, 0.0))];
+        for (x, y, color) in samples {
+            reference.add_sample(x, y, color);
+        }
+
+        for tile in crate::tile::generate_tiles(&Tile::from_resolution(&Resolution::new(8, 8)), 4, crate::tile::TileOrder::Scanline) {
+            let mut film_tile = tiled.create_tile(&tile);
+            for (x, y, color) in samples {
+                if (tile.x as f32..(tile.x + tile.width) as f32).contains(&x) && (tile.y as f32..(tile.y + tile.height) as f32).contains(&y) {
+                    film_tile.add_sample(x, y, color);
+                }
+            }
+
+            tiled.merge_tile(film_tile);
+        }
+
+        assert_eq!(reference.to_image(), tiled.to_image());
+    }
+
+    #[test]
+    fn test_regions_stop_splats() {
+        let mut film = Film::new(Resolution::new(4, 1), ReconstructionFilter::new(FilterKind::Tent, 1.5));
+        film.set_regions(vec![Tile { x: 0, y: 0, width: 2, height: 1 }, Tile { x: 2, y: 0, width: 2, height: 1 }]);
+        film.add_sample(1.9, 0.5, Vec3::new(1.0, 1.0, 1.0));
+
+        let mut tile = film.create_tile(&Tile { x: 2, y: 0, width: 2, height: 1 });
+        tile.add_sample(2.1, 0.5, Vec3::new(0.0, 0.0, 1.0));
+        film.merge_tile(tile);
+
+        let image = film.to_image();
+        assert_eq!(image.get_pixel(1, 0).0, [1.0, 1.0, 1.0]);
+        assert_eq!(image.get_pixel(2, 0).0, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_sample_outside_film() {
+        let mut film = Film::new(Resolution::new(2, 2), ReconstructionFilter::new(FilterKind::Tent, 1.0));
+        film.add_sample(-5.0, -5.0, Vec3::new(1.0, 1.0, 1.0));
+        film.add_sample(10.0, 10.0, Vec3::new(1.0, 1.0, 1.0));
+
+        assert!(film.to_image().pixels().all(|pixel| pixel.0 == [0.0, 0.0, 0.0]));
+    }
+}
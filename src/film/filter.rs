@@ -0,0 +1,124 @@
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian { alpha: f32 },
+    MitchellNetravali { b: f32, c: f32 },
+    Lanczos { tau: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReconstructionFilter {
+    kind: FilterKind,
+    radius: f32,
+}
+
+impl Default for ReconstructionFilter {
+    fn default() -> Self {
+        // Matches plain per pixel averaging
+        ReconstructionFilter::new(FilterKind::Box, 0.5)
+    }
+}
+
+impl ReconstructionFilter {
+    /// Radii below half a pixel are raised to 0.5, a smaller filter could miss pixel centers entirely and
+    /// leave them without samples
+    pub fn new(kind: FilterKind, radius: f32) -> Self {
+        ReconstructionFilter {
+            kind,
+            radius: f32::max(radius, 0.5),
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Filter weight for a sample offset (in pixels) from the pixel center
+    pub fn evaluate(&self, dx: f32, dy: f32) -> f32 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let x = f32::abs(x);
+        if x > self.radius {
+            return 0.0
+        }
+
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => self.radius - x,
+            FilterKind::Gaussian { alpha } => {
+                // Subtract the value at the radius so the filter falls off to 0 at its edge
+                f32::max(0.0, f32::exp(-alpha * x * x) - f32::exp(-alpha * self.radius * self.radius))
+            },
+            FilterKind::MitchellNetravali { b, c } => Self::mitchell(2.0 * x / self.radius, b, c),
+            FilterKind::Lanczos { tau } => Self::sinc(x) * Self::sinc(x / tau),
+        }
+    }
+
+    fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+        let x = f32::abs(x);
+
+        if x > 1.0 {
+            ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+        }
+        else {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+        }
+    }
+
+    fn sinc(x: f32) -> f32 {
+        if f32::abs(x) < 1e-5 {
+            return 1.0
+        }
+
+        f32::sin(PI * x) / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outside_radius() {
+        let filters = [
+            ReconstructionFilter::new(FilterKind::Box, 0.5),
+            ReconstructionFilter::new(FilterKind::Tent, 1.0),
+            ReconstructionFilter::new(FilterKind::Gaussian { alpha: 2.0 }, 1.5),
+            ReconstructionFilter::new(FilterKind::MitchellNetravali { b: 1.0 / 3.0, c: 1.0 / 3.0 }, 2.0),
+            ReconstructionFilter::new(FilterKind::Lanczos { tau: 3.0 }, 3.0),
+        ];
+
+        for filter in filters {
+            assert_eq!(filter.evaluate(filter.radius() + 0.01, 0.0), 0.0);
+            assert_eq!(filter.evaluate(0.0, -filter.radius() - 0.01), 0.0);
+            assert!(filter.evaluate(0.0, 0.0) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_tent() {
+        let filter = ReconstructionFilter::new(FilterKind::Tent, 1.0);
+
+        assert_eq!(filter.evaluate(0.0, 0.0), 1.0);
+        assert_eq!(filter.evaluate(0.5, 0.0), 0.5);
+    }
+
+    #[test]
+    fn test_mitchell_negative_lobe() {
+        let filter = ReconstructionFilter::new(FilterKind::MitchellNetravali { b: 1.0 / 3.0, c: 1.0 / 3.0 }, 2.0);
+
+        assert!(filter.evaluate(1.5, 0.0) < 0.0);
+    }
+
+    #[test]
+    fn test_radius_clamped_to_half_pixel() {
+        assert_eq!(ReconstructionFilter::new(FilterKind::Box, 0.1).radius(), 0.5);
+        assert_eq!(ReconstructionFilter::new(FilterKind::Tent, -1.0).radius(), 0.5);
+        assert_eq!(ReconstructionFilter::new(FilterKind::Tent, 1.5).radius(), 1.5);
+    }
+}
@@ -0,0 +1,22 @@
+pub mod resolution;
+pub mod camera;
+pub mod ray;
+pub mod animation;
+pub mod interval;
+pub mod aabb;
+pub mod sampling;
+pub mod ray_hit;
+pub mod primitive;
+pub mod material;
+pub mod light;
+pub mod scene;
+pub mod tile;
+pub mod crop;
+pub mod film;
+pub mod output;
+pub mod tonemap;
+pub mod progress;
+pub mod statistics;
+pub mod renderer;
+pub mod sequence;
+pub mod timer;
@@ -1,13 +1,81 @@
 pub mod radial_light;
+pub mod directional_light;
+pub mod spot_light;
+pub mod ies;
+pub mod area_light;
+pub mod environment_light;
+pub mod sky;
+pub mod light_bvh;
 
 use nalgebra_glm::Vec3;
 
+use crate::aabb::Aabb;
+
+/// Sampled point on a light as seen from a shaded point
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub direction: Vec3,    // Normalized, pointing towards the light
+    pub distance: f32,      // Infinite for lights without a position
+    pub pdf: f32,           // Density per unit solid angle of picking `direction`, infinite for delta lights
+}
+
+/// Total emitted power of a light, lumens are converted with the 683 lm/W efficacy of 555 nm light
+#[derive(Debug, Clone, Copy)]
+pub enum LightPower {
+    Watts(f32),
+    Lumens(f32),
+}
+
+impl LightPower {
+    pub fn watts(&self) -> f32 {
+        match self {
+            LightPower::Watts(watts) => *watts,
+            LightPower::Lumens(lumens) => lumens / 683.0,
+        }
+    }
+}
+
+/// Which primitives a light illuminates, by their index in the scene
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LightLink {
+    #[default]
+    All,
+    Include(Vec<usize>),
+    Exclude(Vec<usize>),
+}
+
+impl LightLink {
+    pub fn illuminates(&self, primitive_index: usize) -> bool {
+        match self {
+            LightLink::All => true,
+            LightLink::Include(primitives) => primitives.contains(&primitive_index),
+            LightLink::Exclude(primitives) => !primitives.contains(&primitive_index),
+        }
+    }
+}
+
 pub trait Light {
-    fn position(&self, ray_origin: &Vec3) -> Vec3;
+    fn sample(&self, point: &Vec3) -> LightSample;
 
     fn falloff_intensity(&self, distance_squared: f32) -> f32 {
         1.0 / distance_squared
     }
 
-    fn color(&self, light_direction: &Vec3, normal: &Vec3, distance_squared: f32) -> Vec3;
+    fn color(&self, sample: &LightSample, normal: &Vec3) -> Vec3;
+
+    /// Density per unit solid angle with which `sample` would pick `direction` from `point`,
+    /// zero for delta lights which other sampling strategies can never hit
+    fn pdf(&self, _point: &Vec3, _direction: &Vec3) -> f32 {
+        0.0
+    }
+
+    /// Region the light emits from, `None` for lights at infinity which are sampled at every shading point
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Emitted power, used to pick among local lights
+    fn power(&self) -> f32 {
+        0.0
+    }
 }
@@ -0,0 +1,93 @@
+use rand::{thread_rng, Rng};
+use nalgebra_glm::Vec3;
+
+use super::LightSample;
+use super::light_bvh::LightBounds;
+use crate::primitive::Primitive;
+use crate::tonemap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AreaLightSampling {
+    UniformArea,    // Uniform over the surface, simple but wastes samples on parts facing away
+    SolidAngle,     // Uniform over the directions the emitter covers, where the primitive supports it
+}
+
+/// Emissive primitive of the scene sampled for direct lighting, refers to the primitive by index
+#[derive(Debug, Clone, Copy)]
+pub struct AreaLight {
+    primitive_index: usize,
+}
+
+impl AreaLight {
+    pub fn new(primitive_index: usize) -> Self {
+        AreaLight {
+            primitive_index,
+        }
+    }
+
+    /// Primitives with a bounded surface and an emissive material act as area lights
+    pub fn is_emitter(primitive: &dyn Primitive) -> bool {
+        primitive.surface_area().is_some() && primitive.bounds().is_some() && primitive.material().emit() != Vec3::zeros()
+    }
+
+    /// Bounds and power of a diffuse emitter, emitting from both sides
+    pub fn light_bounds(primitive: &dyn Primitive) -> Option<LightBounds> {
+        let power = 2.0 * std::f32::consts::PI * primitive.surface_area()? * tonemap::luminance(&primitive.material().emit());
+
+        Some(LightBounds { bounds: primitive.bounds()?, power })
+    }
+
+    pub fn primitive_index(&self) -> usize {
+        self.primitive_index
+    }
+
+    /// Sample a point on the emitter at `time` and the irradiance it delivers to a surface at `point` facing `normal`
+    pub fn sample(&self, primitive: &dyn Primitive, point: &Vec3, normal: &Vec3, time: f32, sampling: AreaLightSampling) -> Option<(LightSample, Vec3)> {
+        let mut rng = thread_rng();
+        let surface = primitive.sample_surface(point, time, sampling, rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0))?;
+
+        let to_light = surface.position - point;
+        let distance = to_light.magnitude();
+        let direction = to_light / distance;
+
+        let cos_surface = direction.dot(normal);
+        if cos_surface <= 0.0 || surface.pdf <= 0.0 || !surface.pdf.is_finite() {
+            return None
+        }
+
+        let irradiance = primitive.material().emit() * (cos_surface / surface.pdf);
+        Some((LightSample { direction, distance, pdf: surface.pdf }, irradiance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+    use crate::primitive::sphere::Sphere;
+    use crate::material::emissive::Emissive;
+
+    #[test]
+    fn test_strategies_agree() {
+        // A uniformly emitting sphere seen from outside delivers L * pi * (r / d)^2 to a surface facing it
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -4.0), 1.0, Box::new(Emissive::new(Vec3::new(1.0, 1.0, 1.0), 1.0)));
+        let light = AreaLight::new(0);
+        let expected = PI / 16.0;
+
+        for sampling in [AreaLightSampling::UniformArea, AreaLightSampling::SolidAngle] {
+            let count = 50000;
+            let total: f32 = (0..count)
+                .filter_map(|_| light.sample(&sphere, &Vec3::zeros(), &Vec3::new(0.0, 0.0, -1.0), 0.0, sampling))
+                .filter(|(sample, _)| {
+                    // Samples on the far side would be occluded by the sphere itself
+                    let position = sample.direction * sample.distance;
+                    (position - Vec3::new(0.0, 0.0, -4.0)).dot(&sample.direction) < 0.0
+                })
+                .map(|(_, irradiance)| irradiance.x)
+                .sum();
+
+            let estimate = total / count as f32;
+            assert!(f32::abs(estimate - expected) < 0.05 * expected, "{:?} estimated {} instead of {}", sampling, estimate, expected);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use rand::{thread_rng, Rng};
+use nalgebra_glm::Vec3;
+
+use super::{Light, LightSample};
+use crate::sampling;
+
+/// Light arriving from infinitely far away, like the sun
+pub struct DirectionalLight {
+    direction: Vec3,        // Direction the light travels in
+    irradiance: Vec3,       // Irradiance on a surface facing the light
+    cos_half_angle: f32,
+}
+
+impl DirectionalLight {
+    /// `angular_diameter` in degrees softens shadows, the sun is about 0.53 degrees
+    pub fn new(direction: Vec3, irradiance: Vec3, angular_diameter: f32) -> Self {
+        DirectionalLight {
+            direction: direction.normalize(),
+            irradiance,
+            cos_half_angle: f32::cos(f32::to_radians(f32::clamp(angular_diameter, 0.0, 180.0)) / 2.0),
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: &Vec3) -> LightSample {
+        let mut rng = thread_rng();
+        let direction = sampling::uniform_cone(&-self.direction, self.cos_half_angle, rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+
+        LightSample {
+            direction,
+            distance: f32::INFINITY,
+            pdf: if self.cos_half_angle >= 1.0 { f32::INFINITY } else { sampling::uniform_cone_pdf(self.cos_half_angle) },
+        }
+    }
+
+    fn color(&self, sample: &LightSample, normal: &Vec3) -> Vec3 {
+        f32::max(sample.direction.dot(normal), 0.0) * self.irradiance
+    }
+
+    fn pdf(&self, _point: &Vec3, direction: &Vec3) -> f32 {
+        if self.cos_half_angle < 1.0 && direction.dot(&-self.direction) >= self.cos_half_angle {
+            sampling::uniform_cone_pdf(self.cos_half_angle)
+        }
+        else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_within_sun_disk() {
+        let light = DirectionalLight::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 10.0);
+        let cos_half_angle = f32::cos(f32::to_radians(5.0));
+
+        for _ in 0..100 {
+            let sample = light.sample(&Vec3::zeros());
+
+            assert_eq!(sample.distance, f32::INFINITY);
+            assert!(sample.direction.y >= cos_half_angle - 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_irradiance_falls_off_with_angle() {
+        let light = DirectionalLight::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(2.0, 2.0, 2.0), 0.0);
+        let sample = light.sample(&Vec3::zeros());
+
+        assert!((light.color(&sample, &Vec3::new(0.0, 1.0, 0.0)) - Vec3::new(2.0, 2.0, 2.0)).magnitude() < 1e-5);
+        assert_eq!(light.color(&sample, &Vec3::new(0.0, -1.0, 0.0)), Vec3::zeros());
+    }
+}
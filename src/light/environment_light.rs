@@ -0,0 +1,190 @@
+use std::f32::consts::PI;
+use std::path::Path;
+use rand::{thread_rng, Rng};
+use nalgebra_glm::Vec3;
+use image::ImageResult;
+
+use super::{Light, LightSample};
+use crate::sampling::Distribution2D;
+use crate::tonemap;
+
+/// Image based lighting from an equirectangular HDR map surrounding the scene,
+/// sampled proportionally to its luminance
+pub struct EnvironmentLight {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    distribution: Distribution2D,
+    rotation: f32,  // Radians around the world up axis
+    intensity: f32,
+    visible_to_camera: bool,
+}
+
+impl EnvironmentLight {
+    pub fn load(path: &Path) -> ImageResult<Self> {
+        let image = image::open(path)?.to_rgb32f();
+        let (width, height) = image.dimensions();
+        let pixels = image.pixels().map(|pixel| Vec3::new(pixel.0[0], pixel.0[1], pixel.0[2])).collect();
+
+        Ok(EnvironmentLight::new(width as usize, height as usize, pixels))
+    }
+
+    /// Row major linear radiance, top row first, the center column faces -z like the equirectangular camera
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(pixels.len(), width * height, "Environment map size does not match its dimensions");
+
+        // Rows near the poles cover less solid angle, weight them by sin(theta) so bright polar pixels are not oversampled
+        let weights: Vec<f32> = pixels.iter()
+            .enumerate()
+            .map(|(index, color)| {
+                let theta = PI * ((index / width) as f32 + 0.5) / height as f32;
+                tonemap::luminance(color) * f32::sin(theta)
+            })
+            .collect();
+
+        EnvironmentLight {
+            width,
+            height,
+            distribution: Distribution2D::new(width, height, &weights),
+            pixels,
+            rotation: 0.0,
+            intensity: 1.0,
+            visible_to_camera: true,
+        }
+    }
+
+    /// Rotate the map around the world up axis, in degrees
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = f32::to_radians(rotation);
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Hidden maps still light the scene but camera rays see the scene's sky gradient instead
+    pub fn with_camera_visibility(mut self, visible_to_camera: bool) -> Self {
+        self.visible_to_camera = visible_to_camera;
+        self
+    }
+
+    pub fn is_visible_to_camera(&self) -> bool {
+        self.visible_to_camera
+    }
+
+    /// Radiance arriving from `direction`
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.direction_to_uv(direction);
+
+        let x = usize::min((u * self.width as f32) as usize, self.width - 1);
+        let y = usize::min((v * self.height as f32) as usize, self.height - 1);
+
+        self.intensity * self.pixels[y * self.width + x]
+    }
+
+    /// Density per unit solid angle of sampling `direction`
+    pub fn pdf(&self, direction: &Vec3) -> f32 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = f32::sin(v * PI);
+        if sin_theta <= 0.0 {
+            return 0.0
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn direction_to_uv(&self, direction: &Vec3) -> (f32, f32) {
+        let direction = direction.normalize();
+
+        let longitude = f32::atan2(direction.x, -direction.z) - self.rotation;
+        let latitude = f32::asin(f32::clamp(direction.y, -1.0, 1.0));
+
+        ((longitude / (2.0 * PI) + 0.5).rem_euclid(1.0), 0.5 - latitude / PI)
+    }
+
+    fn uv_to_direction(&self, u: f32, v: f32) -> Vec3 {
+        let longitude = (u - 0.5) * 2.0 * PI + self.rotation;
+        let latitude = (0.5 - v) * PI;
+
+        Vec3::new(
+            f32::cos(latitude) * f32::sin(longitude),
+            f32::sin(latitude),
+            -f32::cos(latitude) * f32::cos(longitude),
+        )
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn sample(&self, _point: &Vec3) -> LightSample {
+        let mut rng = thread_rng();
+        let ((u, v), _) = self.distribution.sample_continuous(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+
+        let direction = self.uv_to_direction(u, v);
+
+        LightSample {
+            direction,
+            distance: f32::INFINITY,
+            pdf: EnvironmentLight::pdf(self, &direction),
+        }
+    }
+
+    fn color(&self, sample: &LightSample, normal: &Vec3) -> Vec3 {
+        let pdf = sample.pdf;
+        let cos_surface = sample.direction.dot(normal);
+        if pdf <= 0.0 || cos_surface <= 0.0 {
+            return Vec3::zeros()
+        }
+
+        self.radiance(&sample.direction) * (cos_surface / pdf)
+    }
+
+    fn pdf(&self, _point: &Vec3, direction: &Vec3) -> f32 {
+        EnvironmentLight::pdf(self, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_mapping_roundtrip() {
+        let environment = EnvironmentLight::new(1, 1, vec![Vec3::new(1.0, 1.0, 1.0)]).with_rotation(30.0);
+
+        for direction in [Vec3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 0.5, 0.0), Vec3::new(-0.3, -0.8, 0.6)] {
+            let direction = direction.normalize();
+            let (u, v) = environment.direction_to_uv(&direction);
+
+            assert!((environment.uv_to_direction(u, v) - direction).magnitude() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_uniform_map_irradiance() {
+        // A constant environment of radiance 1 delivers pi to a surface facing up
+        let environment = EnvironmentLight::new(8, 4, vec![Vec3::new(1.0, 1.0, 1.0); 32]);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        let count = 20000;
+        let total: f32 = (0..count)
+            .map(|_| environment.color(&environment.sample(&Vec3::zeros()), &normal).x)
+            .sum();
+
+        assert!(f32::abs(total / count as f32 - PI) < 0.05 * PI);
+    }
+
+    #[test]
+    fn test_samples_follow_bright_pixels() {
+        // Only the pixel straight ahead is lit
+        let mut pixels = vec![Vec3::zeros(); 16];
+        pixels[4 * 2 + 2] = Vec3::new(10.0, 10.0, 10.0);
+        let environment = EnvironmentLight::new(4, 4, pixels);
+
+        for _ in 0..100 {
+            let sample = environment.sample(&Vec3::zeros());
+            assert!(environment.radiance(&sample.direction).x > 0.0);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::Path;
+
+/// Photometric web from an IES LM-63 file, candela by vertical and horizontal angle
+#[derive(Debug, Clone)]
+pub struct IesProfile {
+    vertical_angles: Vec<f32>,      // Degrees, 0 points along the light axis
+    horizontal_angles: Vec<f32>,    // Degrees around the light axis
+    candela: Vec<Vec<f32>>,         // One row of vertical samples per horizontal angle
+    max_candela: f32,
+}
+
+#[derive(Debug)]
+pub enum IesError {
+    Io(std::io::Error),
+    MissingTilt,
+    InvalidData(&'static str),
+}
+
+impl IesProfile {
+    pub fn load(path: &Path) -> Result<Self, IesError> {
+        let contents = std::fs::read_to_string(path).map_err(IesError::Io)?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, IesError> {
+        // Keywords up to the TILT line are free form, everything after it is a list of numbers
+        let mut lines = contents.lines();
+        let tilt = lines.find(|line| line.trim_start().starts_with("TILT=")).ok_or(IesError::MissingTilt)?;
+
+        let mut values = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<f32>().map_err(|_| IesError::InvalidData("non numeric value")));
+        let mut next = move || values.next().unwrap_or(Err(IesError::InvalidData("unexpected end of file")));
+
+        if tilt.trim() == "TILT=INCLUDE" {
+            // Lamp tilt factors only matter for lamps mounted at an angle, skip them
+            next()?;
+            let count = next()? as usize;
+            for _ in 0..(2 * count) {
+                next()?;
+            }
+        }
+
+        let _lamp_count = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let _photometric_type = next()?;
+        for _ in 0..4 {
+            next()?;    // Units and luminous opening dimensions
+        }
+        let ballast_factor = next()?;
+        let _ballast_lamp_factor = next()?;
+        let _input_watts = next()?;
+
+        if vertical_count < 2 || horizontal_count < 1 {
+            return Err(IesError::InvalidData("too few angles"))
+        }
+
+        let vertical_angles = (0..vertical_count).map(|_| next()).collect::<Result<Vec<_>, _>>()?;
+        let horizontal_angles = (0..horizontal_count).map(|_| next()).collect::<Result<Vec<_>, _>>()?;
+        let candela = (0..horizontal_count)
+            .map(|_| (0..vertical_count).map(|_| next().map(|value| value * multiplier * ballast_factor)).collect())
+            .collect::<Result<Vec<Vec<f32>>, _>>()?;
+
+        let sorted = |angles: &[f32]| angles.windows(2).all(|pair| pair[0] < pair[1]);
+        if !sorted(&vertical_angles) || !sorted(&horizontal_angles) {
+            return Err(IesError::InvalidData("angles are not increasing"))
+        }
+
+        let max_candela = candela.iter().flatten().fold(0.0, |max: f32, value| f32::max(max, *value));
+
+        Ok(IesProfile {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_candela,
+        })
+    }
+
+    pub fn max_candela(&self) -> f32 {
+        self.max_candela
+    }
+
+    /// Luminous intensity in candela, angles in degrees
+    pub fn evaluate(&self, vertical_angle: f32, horizontal_angle: f32) -> f32 {
+        let horizontal_angle = self.fold_horizontal(horizontal_angle);
+
+        let (h0, h1, ht) = Self::bracket(&self.horizontal_angles, horizontal_angle);
+        let (v0, v1, vt) = Self::bracket(&self.vertical_angles, vertical_angle);
+
+        let row = |h: usize| self.candela[h][v0] * (1.0 - vt) + self.candela[h][v1] * vt;
+        row(h0) * (1.0 - ht) + row(h1) * ht
+    }
+
+    /// Intensity relative to the brightest direction
+    pub fn evaluate_normalized(&self, vertical_angle: f32, horizontal_angle: f32) -> f32 {
+        if self.max_candela <= 0.0 {
+            return 0.0
+        }
+
+        self.evaluate(vertical_angle, horizontal_angle) / self.max_candela
+    }
+
+    fn fold_horizontal(&self, angle: f32) -> f32 {
+        let angle = angle.rem_euclid(360.0);
+
+        // The last horizontal angle tells which symmetry the file relies on
+        match self.horizontal_angles[self.horizontal_angles.len() - 1] as u32 {
+            0 => 0.0,
+            90 => {
+                let angle = if angle > 180.0 { 360.0 - angle } else { angle };
+                if angle > 90.0 { 180.0 - angle } else { angle }
+            },
+            180 => if angle > 180.0 { 360.0 - angle } else { angle },
+            _ => angle,
+        }
+    }
+
+    /// Indices of the samples around `value` and the position between them, clamped to the sampled range
+    fn bracket(angles: &[f32], value: f32) -> (usize, usize, f32) {
+        let last = angles.len() - 1;
+        if value <= angles[0] {
+            return (0, 0, 0.0)
+        }
+        if value >= angles[last] {
+            return (last, last, 0.0)
+        }
+
+        let next = angles.partition_point(|angle| *angle <= value);
+        let (a, b) = (angles[next - 1], angles[next]);
+
+        (next - 1, next, (value - a) / (b - a))
+    }
+}
+
+impl std::fmt::Display for IesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IesError::Io(error) => write!(f, "failed to read IES profile: {}", error),
+            IesError::MissingTilt => write!(f, "IES profile has no TILT line"),
+            IesError::InvalidData(reason) => write!(f, "invalid IES profile: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for IesError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] Downlight
+[MANUFAC] Example
+TILT=NONE
+1 1000 2.0 3 2 1 2 0.1 0.1 0.0
+1.0 1.0 20
+0 45 90
+0 90
+100 50 0
+80 40, 0
+";
+
+    #[test]
+    fn test_parse_and_interpolate() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+
+        assert_eq!(profile.max_candela(), 200.0);
+        assert_eq!(profile.evaluate(0.0, 0.0), 200.0);
+        assert_eq!(profile.evaluate(22.5, 0.0), 150.0);
+        assert_eq!(profile.evaluate(120.0, 0.0), 0.0);
+        assert_eq!(profile.evaluate(0.0, 45.0), 180.0);
+
+        // Quadrant symmetry mirrors the measured quarter
+        assert_eq!(profile.evaluate(0.0, 270.0), profile.evaluate(0.0, 90.0));
+        assert_eq!(profile.evaluate(0.0, 135.0), profile.evaluate(0.0, 45.0));
+    }
+
+    #[test]
+    fn test_reject_invalid_files() {
+        assert!(matches!(IesProfile::parse("IESNA:LM-63-2002\n1 2 3"), Err(IesError::MissingTilt)));
+        assert!(matches!(IesProfile::parse("TILT=NONE\n1 1000 1"), Err(IesError::InvalidData(_))));
+    }
+}
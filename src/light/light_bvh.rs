@@ -0,0 +1,157 @@
+use nalgebra_glm::Vec3;
+
+use crate::aabb::Aabb;
+
+/// Bounds and emitted power of one light, as seen by the light hierarchy
+#[derive(Debug, Clone, Copy)]
+pub struct LightBounds {
+    pub bounds: Aabb,
+    pub power: f32,
+}
+
+/// Hierarchy over local lights, traversed stochastically towards the lights that matter most for a shaded point.
+/// Picking a light costs O(log n) no matter how many lights the scene has
+#[derive(Debug, Clone)]
+pub struct LightBvh {
+    nodes: Vec<LightBvhNode>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LightBvhNode {
+    bounds: Aabb,
+    power: f32,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NodeKind {
+    Leaf(usize),            // Index of the light
+    Interior(usize, usize), // Node indices of the children
+}
+
+impl LightBvh {
+    pub fn new(lights: &[LightBounds]) -> Self {
+        let mut bvh = LightBvh { nodes: Vec::with_capacity(2 * lights.len()) };
+
+        let mut indices: Vec<usize> = (0..lights.len()).filter(|&index| lights[index].power > 0.0).collect();
+        if !indices.is_empty() {
+            bvh.build(lights, &mut indices);
+        }
+
+        bvh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Pick a light for `point`, returns the light index and the probability of picking it
+    pub fn sample(&self, point: &Vec3, u: f32) -> Option<(usize, f32)> {
+        if self.is_empty() {
+            return None
+        }
+
+        let (mut node, mut pmf, mut u) = (0, 1.0, u);
+        loop {
+            match self.nodes[node].kind {
+                NodeKind::Leaf(light) => return Some((light, pmf)),
+                NodeKind::Interior(left, right) => {
+                    let left_importance = self.nodes[left].importance(point);
+                    let right_importance = self.nodes[right].importance(point);
+                    let total = left_importance + right_importance;
+
+                    let left_probability = if total > 0.0 { left_importance / total } else { 0.5 };
+                    if u < left_probability {
+                        (node, pmf, u) = (left, pmf * left_probability, u / left_probability);
+                    }
+                    else {
+                        let right_probability = 1.0 - left_probability;
+                        (node, pmf, u) = (right, pmf * right_probability, (u - left_probability) / right_probability);
+                    }
+
+                    u = f32::min(u, 1.0 - f32::EPSILON);
+                },
+            }
+        }
+    }
+
+    fn build(&mut self, lights: &[LightBounds], indices: &mut [usize]) -> usize {
+        let bounds = indices.iter()
+            .map(|&index| lights[index].bounds)
+            .reduce(|a, b| a.union(&b))
+            .unwrap();
+        let power = indices.iter().map(|&index| lights[index].power).sum();
+
+        let node = self.nodes.len();
+        if let [light] = indices {
+            self.nodes.push(LightBvhNode { bounds, power, kind: NodeKind::Leaf(*light) });
+            return node
+        }
+
+        // Median split along the axis where the light centers spread the most
+        let centers = indices.iter()
+            .map(|&index| Aabb::from_point(lights[index].bounds.center()))
+            .reduce(|a, b| a.union(&b))
+            .unwrap();
+        let axis = centers.longest_axis();
+        indices.sort_by(|&a, &b| lights[a].bounds.center()[axis].total_cmp(&lights[b].bounds.center()[axis]));
+
+        self.nodes.push(LightBvhNode { bounds, power, kind: NodeKind::Leaf(0) });
+        let (left_indices, right_indices) = indices.split_at_mut(indices.len() / 2);
+        let left = self.build(lights, left_indices);
+        let right = self.build(lights, right_indices);
+        self.nodes[node].kind = NodeKind::Interior(left, right);
+
+        node
+    }
+}
+
+impl LightBvhNode {
+    fn importance(&self, point: &Vec3) -> f32 {
+        // Inverse square distance to the cluster, clamped inside it so nearby clusters do not blow up
+        let half_diagonal = self.bounds.diagonal().magnitude() / 2.0;
+        let distance_squared = f32::max((point - self.bounds.center()).magnitude_squared(), half_diagonal * half_diagonal);
+
+        self.power / f32::max(distance_squared, 1e-6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_light(x: f32, power: f32) -> LightBounds {
+        LightBounds { bounds: Aabb::from_point(Vec3::new(x, 0.0, 0.0)), power }
+    }
+
+    #[test]
+    fn test_prefers_nearby_lights() {
+        let lights: Vec<LightBounds> = (0..64).map(|i| point_light(i as f32 * 10.0, 1.0)).collect();
+        let bvh = LightBvh::new(&lights);
+
+        let (light, pmf) = bvh.sample(&Vec3::new(0.0, 1.0, 0.0), 0.1).unwrap();
+        assert!(light < 2, "picked light {}", light);
+        assert!(pmf > 0.5);
+    }
+
+    #[test]
+    fn test_pmf_matches_frequency() {
+        let lights = vec![point_light(0.0, 1.0), point_light(1.0, 3.0), point_light(5.0, 0.0), point_light(8.0, 2.0)];
+        let bvh = LightBvh::new(&lights);
+        let point = Vec3::new(2.0, 1.0, 0.0);
+
+        let count = 10000;
+        let mut picks = [0; 4];
+        let mut pmfs = [0.0; 4];
+        for i in 0..count {
+            let (light, pmf) = bvh.sample(&point, (i as f32 + 0.5) / count as f32).unwrap();
+            picks[light] += 1;
+            pmfs[light] = pmf;
+        }
+
+        assert_eq!(picks[2], 0);
+        for light in [0, 1, 3] {
+            assert!(f32::abs(picks[light] as f32 / count as f32 - pmfs[light]) < 0.01);
+        }
+    }
+}
@@ -1,60 +1,174 @@
-use rand::Rng;
+use std::f32::consts::PI;
+use rand::{thread_rng, Rng};
 use nalgebra_glm::Vec3;
 
-use super::Light;
+use super::{Light, LightSample, LightPower};
+use crate::aabb::Aabb;
+use crate::sampling;
+use crate::tonemap;
 
+/// Spherical light emitting uniformly from its surface, a radius of zero makes it a point light
 pub struct RadialLight {
     position: Vec3,
-    color: Vec3,
     radius: f32,
-    base_intensity: f32,
+    power: f32,     // Watts
+    emission: Vec3, // Radiance of the surface, or intensity for a point light
 }
 
 impl RadialLight {
-    pub fn new(position: Vec3, color: Vec3, radius: f32, base_intensity: f32) -> Self {
+    /// `color` only tints the light, its brightness comes from `power`
+    pub fn new(position: Vec3, color: Vec3, radius: f32, power: LightPower) -> Self {
+        let radius = f32::max(radius, 0.0);
+        let power = power.watts();
+
+        let color_luminance = tonemap::luminance(&color);
+        let tint = if color_luminance > 0.0 { color / color_luminance } else { Vec3::zeros() };
+
+        // A diffuse sphere spreads its power over 4 pi r^2 of surface and pi steradians of projected solid angle
+        let emission = if radius > 0.0 {
+            tint * (power / (4.0 * PI * PI * radius * radius))
+        }
+        else {
+            tint * (power / (4.0 * PI))
+        };
+
         RadialLight {
             position,
-            color,
             radius,
-            base_intensity,
+            power,
+            emission,
         }
     }
 
-    fn random_vector(&self, min: f32, max: f32) -> Vec3 {
-        let mut rng = rand::thread_rng();
-        Vec3::new(
-            rng.gen_range(min..max),
-            rng.gen_range(min..max),
-            rng.gen_range(min..max)
-        )
+    /// Distance along `direction` from `point` to the first surface of the sphere facing it
+    fn distance_to_surface(&self, point: &Vec3, direction: &Vec3) -> f32 {
+        let offset = point - self.position;
+        let b = offset.dot(direction);
+        let c = offset.magnitude_squared() - self.radius * self.radius;
+        let root = f32::sqrt(f32::max(b * b - c, 0.0));   // Clamped, directions on the cone's edge graze the sphere
+
+        if -b - root > 0.0 { -b - root } else { -b + root }
     }
 
-    fn sample_light_sphere(&self) -> Vec3 {
-        loop {
-            let vec = self.random_vector(-1.0, 1.0);
+    /// Cosine of the half angle the sphere subtends from `point`, `None` when the point is inside
+    fn cos_theta_max(&self, point: &Vec3) -> Option<f32> {
+        let distance_squared = (self.position - point).magnitude_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None
+        }
 
-            if vec.magnitude_squared() < self.radius {
-                return vec * self.radius;
+        Some(f32::sqrt(1.0 - radius_squared / distance_squared))
+    }
+}
+
+impl Light for RadialLight {
+    fn sample(&self, point: &Vec3) -> LightSample {
+        let to_center = self.position - point;
+
+        if self.radius == 0.0 {
+            return LightSample {
+                direction: to_center.normalize(),
+                distance: to_center.magnitude(),
+                pdf: f32::INFINITY,
             }
         }
+
+        // Sample the cone of directions the sphere covers, so every sample hits the visible side
+        let mut rng = thread_rng();
+        let (u, v) = (rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+        let (direction, pdf) = match self.cos_theta_max(point) {
+            Some(cos_theta_max) => (sampling::uniform_cone(&to_center.normalize(), cos_theta_max, u, v), sampling::uniform_cone_pdf(cos_theta_max)),
+            None => (sampling::uniform_sphere(u, v), 1.0 / (4.0 * PI)),
+        };
+
+        LightSample {
+            direction,
+            distance: self.distance_to_surface(point, &direction),
+            pdf,
+        }
+    }
+
+    fn color(&self, sample: &LightSample, normal: &Vec3) -> Vec3 {
+        let cos_surface = f32::max(sample.direction.dot(normal), 0.0);
+
+        if self.radius == 0.0 {
+            return cos_surface * self.emission * self.falloff_intensity(sample.distance * sample.distance)
+        }
+
+        if sample.pdf <= 0.0 {
+            return Vec3::zeros()
+        }
+
+        self.emission * (cos_surface / sample.pdf)
+    }
+
+    fn pdf(&self, point: &Vec3, direction: &Vec3) -> f32 {
+        if self.radius == 0.0 {
+            return 0.0
+        }
+
+        match self.cos_theta_max(point) {
+            Some(cos_theta_max) if direction.normalize().dot(&(self.position - point).normalize()) >= cos_theta_max => sampling::uniform_cone_pdf(cos_theta_max),
+            Some(_) => 0.0,
+            None => 1.0 / (4.0 * PI),
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around(self.position, self.radius))
+    }
+
+    fn power(&self) -> f32 {
+        self.power
     }
 }
 
-impl Light for RadialLight {
-    fn position(&self, ray_origin: &Vec3) -> Vec3 {
-        let direction = self.position - ray_origin;
-        let mut sphere_sample = self.sample_light_sphere();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_hit_visible_side() {
+        let light = RadialLight::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(1.0, 1.0, 1.0), 1.0, LightPower::Watts(100.0));
+        let point = Vec3::zeros();
 
-        // Rays point in same direction -> sampled 'back' of light
-        if direction.dot(&sphere_sample) > 0.0 {
-            sphere_sample = -1.0 * sphere_sample;   // Flip sample to sample 'front' of light
+        for _ in 0..100 {
+            let sample = light.sample(&point);
+            let on_surface = point + sample.direction * sample.distance;
+
+            assert!(f32::abs((on_surface - light.position).magnitude() - 1.0) < 1e-3);
+            assert!(on_surface.z > -5.0);
+            assert!(f32::abs(sample.pdf - light.pdf(&point, &sample.direction)) < 1e-3 * sample.pdf);
         }
 
-        self.position + sphere_sample
+        assert_eq!(light.pdf(&point, &Vec3::new(0.0, 1.0, 0.0)), 0.0);
     }
 
-    fn color(&self, light_direction: &Vec3, normal: &Vec3, distance_squared: f32) -> Vec3 {
-        let phong = light_direction.dot(normal);
-        phong * self.base_intensity * self.color * self.falloff_intensity(distance_squared)
+    #[test]
+    fn test_irradiance_matches_power() {
+        // A sphere delivers the same irradiance as a point light of equal power, I / d^2 with I = P / 4 pi
+        let power = 4.0 * PI * 100.0;
+        let light = RadialLight::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 2.0, LightPower::Watts(power));
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        let count = 20000;
+        let total: f32 = (0..count)
+            .map(|_| light.color(&light.sample(&Vec3::zeros()), &normal).x)
+            .sum();
+        assert!(f32::abs(total / count as f32 - 4.0) < 0.05 * 4.0);
+
+        let point_light = RadialLight::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 0.0, LightPower::Watts(power));
+        assert!(f32::abs(point_light.color(&point_light.sample(&Vec3::zeros()), &normal).x - 4.0) < 1e-4);
+    }
+
+    #[test]
+    fn test_units_and_facing() {
+        let light = RadialLight::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.2, 0.4, 1.0), 1.0, LightPower::Lumens(683.0));
+        assert!(f32::abs(light.power() - 1.0) < 1e-6);
+
+        // Surfaces facing away receive nothing
+        let sample = light.sample(&Vec3::zeros());
+        assert_eq!(light.color(&sample, &Vec3::new(0.0, -1.0, 0.0)), Vec3::zeros());
     }
 }
@@ -0,0 +1,221 @@
+use std::f32::consts::PI;
+use nalgebra_glm::{Vec3, Mat3};
+
+use super::environment_light::EnvironmentLight;
+
+const SUN_ANGULAR_DIAMETER: f32 = 0.53;     // Degrees
+const SUN_LUMINANCE: f32 = 2.0e6;           // Outside the atmosphere, kcd/m^2
+const WAVELENGTHS: [f32; 3] = [0.68, 0.55, 0.44];   // Micrometers, representative for red, green and blue
+
+/// Preetham et al. analytic daylight sky with a sun disk. Radiance is luminance in kcd/m^2,
+/// so a midday sky needs around -7 EV of exposure compensation
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicalSky {
+    pub sun_elevation: f32, // Degrees above the horizon
+    pub sun_azimuth: f32,   // Degrees, 0 towards -z and 90 towards +x
+    pub turbidity: f32,     // Haziness, 2 is a very clear sky and 10 a hazy one
+    pub ground_albedo: f32,
+}
+
+/// Perez distribution coefficients A to E
+struct Perez([f32; 5]);
+
+impl Perez {
+    fn evaluate(&self, cos_theta: f32, gamma: f32) -> f32 {
+        let [a, b, c, d, e] = self.0;
+        let cos_gamma = f32::cos(gamma);
+
+        (1.0 + a * f32::exp(b / f32::max(cos_theta, 0.01))) * (1.0 + c * f32::exp(d * gamma) + e * cos_gamma * cos_gamma)
+    }
+}
+
+impl PhysicalSky {
+    pub fn new(sun_elevation: f32, sun_azimuth: f32, turbidity: f32, ground_albedo: f32) -> Self {
+        PhysicalSky {
+            sun_elevation,
+            sun_azimuth,
+            turbidity: f32::clamp(turbidity, 1.7, 10.0),   // Range the model was fitted to
+            ground_albedo,
+        }
+    }
+
+    /// Unit vector pointing towards the sun
+    pub fn sun_direction(&self) -> Vec3 {
+        let elevation = f32::to_radians(self.sun_elevation);
+        let azimuth = f32::to_radians(self.sun_azimuth);
+
+        Vec3::new(
+            f32::cos(elevation) * f32::sin(azimuth),
+            f32::sin(elevation),
+            -f32::cos(elevation) * f32::cos(azimuth),
+        )
+    }
+
+    /// Radiance of the sun disk after the atmosphere's Rayleigh and aerosol extinction
+    pub fn sun_radiance(&self) -> Vec3 {
+        let zenith = f32::to_radians(90.0 - f32::max(self.sun_elevation, 0.0));
+
+        // Kasten's relative optical air mass, well behaved towards the horizon
+        let air_mass = 1.0 / (f32::cos(zenith) + 0.15 * f32::powf(93.885 - f32::to_degrees(zenith), -1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+
+        let transmittance = WAVELENGTHS.map(|wavelength| {
+            let rayleigh = f32::exp(-0.008735 * air_mass * f32::powf(wavelength, -4.08));
+            let aerosol = f32::exp(-beta * air_mass * f32::powf(wavelength, -1.3));
+            rayleigh * aerosol
+        });
+
+        SUN_LUMINANCE * Vec3::new(transmittance[0], transmittance[1], transmittance[2])
+    }
+
+    /// Sky radiance from `direction` above the horizon, without the sun disk
+    pub fn sky_radiance(&self, direction: &Vec3) -> Vec3 {
+        let t = self.turbidity;
+        let sun_direction = self.sun_direction();
+        let sun_zenith = f32::acos(f32::clamp(sun_direction.y, -1.0, 1.0));
+
+        let cos_theta = f32::max(direction.y, 0.0);
+        let gamma = f32::acos(f32::clamp(direction.dot(&sun_direction), -1.0, 1.0));
+
+        let luminance = Perez([0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703]);
+        let chromaticity_x = Perez([-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452]);
+        let chromaticity_y = Perez([-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529]);
+
+        let (zenith_luminance, zenith_x, zenith_y) = self.zenith_values(sun_zenith);
+        let relative = |perez: &Perez| perez.evaluate(cos_theta, gamma) / perez.evaluate(1.0, sun_zenith);
+
+        let luminance = zenith_luminance * relative(&luminance);
+        let x = zenith_x * relative(&chromaticity_x);
+        let y = zenith_y * relative(&chromaticity_y);
+
+        Self::xyy_to_rgb(x, y, f32::max(luminance, 0.0))
+    }
+
+    /// Bake sky, sun disk and ground into an equirectangular map for lighting the scene
+    pub fn environment(&self, width: usize, height: usize) -> EnvironmentLight {
+        let sun_direction = self.sun_direction();
+        let sun_radiance = self.sun_radiance();
+        let cos_sun_radius = f32::cos(f32::to_radians(SUN_ANGULAR_DIAMETER / 2.0));
+        let pixel_angle = PI / height as f32;
+
+        let direction_at = |u: f32, v: f32| {
+            let longitude = (u - 0.5) * 2.0 * PI;
+            let latitude = (0.5 - v) * PI;
+            Vec3::new(f32::cos(latitude) * f32::sin(longitude), f32::sin(latitude), -f32::cos(latitude) * f32::cos(longitude))
+        };
+
+        let mut pixels = vec![Vec3::zeros(); width * height];
+        for y in 0..height / 2 {
+            for x in 0..width {
+                let center = direction_at((x as f32 + 0.5) / width as f32, (y as f32 + 0.5) / height as f32);
+                let mut radiance = self.sky_radiance(&center);
+
+                // Supersample pixels near the sun, so the disk keeps its energy at any map resolution
+                let near_sun = f32::acos(f32::clamp(center.dot(&sun_direction), -1.0, 1.0)) < f32::to_radians(SUN_ANGULAR_DIAMETER) + 2.0 * pixel_angle;
+                if near_sun && self.sun_elevation > -SUN_ANGULAR_DIAMETER {
+                    let steps = 16;
+                    let covered = (0..steps * steps)
+                        .map(|i| direction_at(
+                            (x as f32 + ((i % steps) as f32 + 0.5) / steps as f32) / width as f32,
+                            (y as f32 + ((i / steps) as f32 + 0.5) / steps as f32) / height as f32,
+                        ))
+                        .filter(|direction| direction.dot(&sun_direction) >= cos_sun_radius)
+                        .count();
+
+                    radiance += sun_radiance * (covered as f32 / (steps * steps) as f32);
+                }
+
+                pixels[y * width + x] = radiance;
+            }
+        }
+
+        // Diffuse ground lit by everything above the horizon
+        let irradiance: Vec3 = (0..height / 2)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let theta = (y as f32 + 0.5) * pixel_angle;
+                let solid_angle = (2.0 * PI / width as f32) * pixel_angle * f32::sin(theta);
+                pixels[y * width + x] * (f32::cos(theta) * solid_angle)
+            })
+            .sum();
+        let ground = irradiance * (self.ground_albedo / PI);
+
+        for pixel in &mut pixels[(height / 2) * width..] {
+            *pixel = ground;
+        }
+
+        EnvironmentLight::new(width, height, pixels)
+    }
+
+    fn zenith_values(&self, sun_zenith: f32) -> (f32, f32, f32) {
+        let t = self.turbidity;
+        let (theta, theta2, theta3) = (sun_zenith, sun_zenith * sun_zenith, sun_zenith * sun_zenith * sun_zenith);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * sun_zenith);
+        let luminance = (4.0453 * t - 4.9710) * f32::tan(chi) - 0.2155 * t + 2.4192;
+
+        let x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta + 0.25886);
+        let y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta + 0.26688);
+
+        (f32::max(luminance, 0.0), x, y)
+    }
+
+    fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Vec3 {
+        if y <= 0.0 {
+            return Vec3::zeros()
+        }
+
+        let xyz = Vec3::new(x * luminance / y, luminance, (1.0 - x - y) * luminance / y);
+        let xyz_to_srgb = Mat3::new(
+            3.240454, -1.537139, -0.4985314,
+            -0.969266, 1.876011, 0.041556,
+            0.0556434, -0.2040259, 1.057225,
+        );
+
+        (xyz_to_srgb * xyz).map(|channel| f32::max(channel, 0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tonemap::luminance;
+
+    #[test]
+    fn test_sky_brightest_around_sun() {
+        let sky = PhysicalSky::new(30.0, 90.0, 3.0, 0.3);
+        let towards_sun = Vec3::new(1.0, 0.6, 0.0).normalize();
+        let away_from_sun = Vec3::new(-1.0, 0.6, 0.0).normalize();
+
+        assert!(luminance(&sky.sky_radiance(&towards_sun)) > luminance(&sky.sky_radiance(&away_from_sun)));
+    }
+
+    #[test]
+    fn test_haze_and_low_sun_redden_sunlight() {
+        let ratio = |sky: PhysicalSky| {
+            let radiance = sky.sun_radiance();
+            radiance.x / radiance.z
+        };
+
+        assert!(ratio(PhysicalSky::new(60.0, 0.0, 8.0, 0.3)) > ratio(PhysicalSky::new(60.0, 0.0, 2.0, 0.3)));
+        assert!(ratio(PhysicalSky::new(5.0, 0.0, 3.0, 0.3)) > ratio(PhysicalSky::new(60.0, 0.0, 3.0, 0.3)));
+    }
+
+    #[test]
+    fn test_baked_sun_disk_and_ground() {
+        let sky = PhysicalSky::new(45.0, 0.0, 3.0, 0.5);
+        let environment = sky.environment(256, 128);
+
+        let sun = environment.radiance(&sky.sun_direction());
+        let sky_radiance = environment.radiance(&Vec3::new(0.0, 1.0, 0.0));
+        assert!(luminance(&sun) > 1000.0 * luminance(&sky_radiance));
+
+        let dark_ground = PhysicalSky::new(45.0, 0.0, 3.0, 0.25).environment(256, 128);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert!(f32::abs(luminance(&environment.radiance(&down)) - 2.0 * luminance(&dark_ground.radiance(&down))) < 1e-2 * luminance(&environment.radiance(&down)));
+    }
+}
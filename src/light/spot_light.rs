@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use nalgebra_glm::Vec3;
+
+use super::{Light, LightSample};
+use super::ies::IesProfile;
+use crate::aabb::Aabb;
+use crate::sampling;
+use crate::tonemap;
+
+/// Point light emitting in a cone, optionally shaped by a measured IES profile
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    color: Vec3,
+    intensity: f32,
+    cos_inner: f32,
+    cos_outer: f32,
+    profile: Option<Arc<IesProfile>>,
+}
+
+impl SpotLight {
+    /// Cone angles in degrees from the spot axis, full intensity inside `inner_angle` fading to zero at `outer_angle`
+    pub fn new(position: Vec3, direction: Vec3, color: Vec3, intensity: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        let outer_angle = f32::clamp(outer_angle, 0.0, 180.0);
+        let inner_angle = f32::clamp(inner_angle, 0.0, outer_angle);
+
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            cos_inner: f32::cos(f32::to_radians(inner_angle)),
+            cos_outer: f32::cos(f32::to_radians(outer_angle)),
+            profile: None,
+        }
+    }
+
+    /// Scale emission by an IES profile, its brightest direction emits `intensity`.
+    /// The profile's 0 degree vertical angle points along the spot direction
+    pub fn with_profile(mut self, profile: Arc<IesProfile>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Emitted intensity towards `emission_direction`, relative to the spot axis
+    fn emission(&self, emission_direction: &Vec3) -> f32 {
+        let cos_theta = emission_direction.dot(&self.direction);
+        let cone = Self::smoothstep(self.cos_outer, self.cos_inner, cos_theta);
+
+        match &self.profile {
+            Some(profile) => {
+                let (tangent, bitangent) = sampling::orthonormal_basis(&self.direction);
+                let vertical_angle = f32::to_degrees(f32::acos(f32::clamp(cos_theta, -1.0, 1.0)));
+                let horizontal_angle = f32::to_degrees(f32::atan2(emission_direction.dot(&bitangent), emission_direction.dot(&tangent)));
+
+                cone * profile.evaluate_normalized(vertical_angle, horizontal_angle)
+            },
+            None => cone,
+        }
+    }
+
+    fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+        if edge1 <= edge0 {
+            return if x >= edge1 { 1.0 } else { 0.0 }
+        }
+
+        let t = f32::clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: &Vec3) -> LightSample {
+        let to_light = self.position - point;
+
+        LightSample {
+            direction: to_light.normalize(),
+            distance: to_light.magnitude(),
+            pdf: f32::INFINITY,
+        }
+    }
+
+    fn color(&self, sample: &LightSample, normal: &Vec3) -> Vec3 {
+        let cos_surface = f32::max(sample.direction.dot(normal), 0.0);
+        let emission = self.emission(&-sample.direction);
+
+        cos_surface * emission * self.intensity * self.color * self.falloff_intensity(sample.distance * sample.distance)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::from_point(self.position))
+    }
+
+    fn power(&self) -> f32 {
+        // Solid angle of the outer cone, an upper bound when a profile shapes the beam
+        2.0 * std::f32::consts::PI * (1.0 - self.cos_outer) * self.intensity * tonemap::luminance(&self.color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(light: &SpotLight, point: Vec3) -> f32 {
+        let sample = light.sample(&point);
+        light.color(&sample, &sample.direction).x
+    }
+
+    #[test]
+    fn test_cone_falloff() {
+        let light = SpotLight::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 1.0, 20.0, 40.0);
+
+        let inside = received(&light, Vec3::new(0.0, 0.0, 0.0));
+        let edge = received(&light, Vec3::new(f32::tan(f32::to_radians(30.0)), 0.0, 0.0));
+        let outside = received(&light, Vec3::new(1.0, 0.0, 0.0));
+
+        assert!(f32::abs(inside - 1.0) < 1e-5);
+        assert!(edge > 0.0 && edge < f32::cos(f32::to_radians(30.0)).powi(2));
+        assert_eq!(outside, 0.0);
+    }
+}
@@ -1,35 +1,28 @@
-mod resolution;
-mod camera;
-mod ray;
-mod interval;
-mod ray_hit;
-mod primitive;
-mod material;
-mod light;
-mod scene;
-mod renderer;
-mod timer;
-
 use std::path::Path;
 use nalgebra_glm::Vec3;
 
-use resolution::Resolution;
-use interval::Interval;
-use camera::{Camera, FocusMode};
-use primitive::{
+use rust_raytracer::resolution::Resolution;
+use rust_raytracer::interval::Interval;
+use rust_raytracer::camera::{CameraOrientation, FocusMode, perspective::PerspectiveCamera};
+use rust_raytracer::primitive::{
     sphere::Sphere,
     plane::{Plane, Rectangle}
 };
-use light::radial_light::RadialLight;
-use material::{
+use rust_raytracer::light::LightPower;
+use rust_raytracer::light::radial_light::RadialLight;
+use rust_raytracer::material::{
     diffuse::LambertianDiffuse,
     metal::Metal,
     dielectric::Dielectric,
     emissive::Emissive
 };
-use scene::{SkyAttenuation, Scene};
-use renderer::{Renderer, RendererConfig};
-use timer::Timer;
+use rust_raytracer::scene::{SkyAttenuation, Scene};
+use rust_raytracer::renderer::{Renderer, RendererConfig};
+use rust_raytracer::film::filter::{ReconstructionFilter, FilterKind};
+use rust_raytracer::tile::TileOrder;
+use rust_raytracer::crop::CropOutput;
+use rust_raytracer::tonemap::{ToneMapping, ToneMapOperator, TransferFunction};
+use rust_raytracer::progress;
 
 fn main() {
     println!("Raytracing in one Weekend!");
@@ -39,21 +32,29 @@ fn main() {
         RendererConfig {
             resolution: render_resolution,
             sample_count: 500,
-            max_bounces: 10
+            max_bounces: 10,
+            tone_mapping: ToneMapping {
+                exposure: 0.0,
+                operator: ToneMapOperator::AcesFilmic,
+                transfer_function: TransferFunction::Srgb,
+            },
+            filter: ReconstructionFilter::new(FilterKind::MitchellNetravali { b: 1.0 / 3.0, c: 1.0 / 3.0 }, 2.0),
+            tile_size: 32,
+            tile_order: TileOrder::Spiral,
+            crop: None,
+            crop_output: CropOutput::CropOnly,
         }
     );
 
-    let camera = Camera::new(
+    let camera = PerspectiveCamera::new(
         Vec3::new(0.0, 2.0, 6.0),
-        Vec3::new(0.0, 1.0, 0.0),
+        CameraOrientation::look_at(Vec3::new(0.0, 1.0, 0.0)),
         60.0,
         FocusMode::AutoFocus,
         1.5,
         Interval::new(0.001, 100.0),
         &render_resolution
-    );
-
-    let mut timer = Timer::new();
+    ).expect("Invalid camera setup");
 
     let scene = Scene::new(
         SkyAttenuation {
@@ -119,27 +120,26 @@ fn main() {
                 Vec3::new(0.0, 5.0, 4.0),
                 Vec3::new(1.0, 1.0, 1.0),
                 2.0,
-                50.0,
+                LightPower::Watts(630.0),
             )),
             Box::new(RadialLight::new(
                 Vec3::new(-4.0, 6.0, -3.0),
                 Vec3::new(0.0, 0.3, 0.8),
                 0.5,
-                50.0,
+                LightPower::Watts(170.0),
             )),
             Box::new(RadialLight::new(
                 Vec3::new(5.0, 8.0, -3.0),
                 Vec3::new(1.0, 0.7, 0.2),
                 0.5,
-                50.0,
+                LightPower::Watts(460.0),
             ))
         ]
     );
 
-    timer.tick();
-    renderer.render(&camera, &scene);
-    timer.tick();
-    println!("Frame time: {:?} ({} FPS)", timer.delta_time(), 1.0 / timer.delta_time_f32());
+    renderer.set_progress_callback(progress::terminal_progress());
+    let statistics = renderer.render(&camera, &scene);
+    println!("{}", statistics);
 
     renderer.save_render(Path::new("result.png"));
 }
@@ -6,7 +6,7 @@ pub mod emissive;
 use rand::Rng;
 use nalgebra_glm::Vec3;
 
-use crate::ray::Ray;
+use crate::ray::{Ray, RayKind};
 use crate::ray_hit::RayHit;
 
 pub enum MaterialTransparency {
@@ -17,6 +17,7 @@ pub enum MaterialTransparency {
 pub struct Scatter {
     pub ray: Ray,
     pub attenuation: Vec3,
+    pub kind: RayKind,
 }
 
 pub trait Material {
@@ -26,6 +27,11 @@ pub trait Material {
         Vec3::zeros()   // By default don't emit light
     }
 
+    /// Reflectance of the material's Lambertian part, which receives light sampled directly from the lights
+    fn diffuse_albedo(&self) -> Vec3 {
+        Vec3::zeros()
+    }
+
     fn material_transparency(&self) -> MaterialTransparency {
         MaterialTransparency::Opaque
     }
@@ -63,7 +69,7 @@ pub trait Material {
         let cos_theta = f32::min(-incoming.dot(normal), 1.0);
 
         let out_perpendicular = ior_fraction * (incoming + cos_theta * normal);
-        let out_parallel = -1.0 * f32::sqrt(f32::abs(1.0 - out_perpendicular.magnitude_squared())) * normal;
+        let out_parallel = -f32::sqrt(f32::abs(1.0 - out_perpendicular.magnitude_squared())) * normal;
 
         out_perpendicular + out_parallel
     }
@@ -2,7 +2,7 @@ use nalgebra_glm::Vec3;
 use rand::Rng;
 
 use super::{Material, Scatter, MaterialTransparency};
-use crate::ray::Ray;
+use crate::ray::{Ray, RayKind};
 use crate::ray_hit::{RayHit, HitType};
 
 
@@ -27,7 +27,7 @@ impl Material for Dielectric {
             HitType::BackFace => self.index_of_refraction / 1.0,
         };
 
-        let cos_theta = f32::min(-1.0 * ray.direction().dot(&hit.normal), 1.0);
+        let cos_theta = f32::min(-ray.direction().dot(&hit.normal), 1.0);
         let sin_theta = f32::sqrt(1.0 - (cos_theta * cos_theta));
         let must_reflect = (ior_fraction * sin_theta) > 1.0;
 
@@ -35,16 +35,17 @@ impl Material for Dielectric {
         let reflect_chance = rng.gen_range(0.0..1.0);
         let reflectance = self.reflectance(cos_theta, ior_fraction);
 
-        let ray_direction = if must_reflect || reflectance > reflect_chance {
-            self.reflect(ray.direction(), &hit.normal)
+        let (ray_direction, kind) = if must_reflect || reflectance > reflect_chance {
+            (self.reflect(ray.direction(), &hit.normal), RayKind::Specular)
         }
         else {
-            self.refract(ray.direction(), &hit.normal, ior_fraction)
+            (self.refract(ray.direction(), &hit.normal, ior_fraction), RayKind::Transmission)
         };
 
         Some(Scatter {
-            ray: Ray::new(hit.position, ray_direction),
+            ray: Ray::with_time(hit.position, ray_direction, ray.time()),
             attenuation: self.albedo,
+            kind,
         })
     }
 
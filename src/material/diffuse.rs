@@ -1,7 +1,7 @@
 use nalgebra_glm::Vec3;
 
 use super::{Material, Scatter};
-use crate::ray::Ray;
+use crate::ray::{Ray, RayKind};
 use crate::ray_hit::RayHit;
 
 pub struct LambertianDiffuse {
@@ -17,7 +17,7 @@ impl LambertianDiffuse {
 }
 
 impl Material for LambertianDiffuse {
-    fn scatter(&self, _ray: &Ray, hit: &RayHit) -> Option<Scatter> {
+    fn scatter(&self, ray: &Ray, hit: &RayHit) -> Option<Scatter> {
         let mut scatter_direction = hit.normal + self.random_unit_vector();
         // If the composite vector is close to  0, use the surface normal
         if self.near_zero(&scatter_direction) {
@@ -25,8 +25,13 @@ impl Material for LambertianDiffuse {
         }
 
         Some(Scatter {
-            ray: Ray::new(hit.position, scatter_direction),
+            ray: Ray::with_time(hit.position, scatter_direction, ray.time()),
             attenuation: self.albedo,
+            kind: RayKind::Diffuse,
         })
     }
+
+    fn diffuse_albedo(&self) -> Vec3 {
+        self.albedo
+    }
 }
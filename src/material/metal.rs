@@ -2,7 +2,7 @@ use nalgebra_glm::Vec3;
 
 use super::{Material, Scatter};
 use crate::interval::Interval;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayKind};
 use crate::ray_hit::RayHit;
 
 pub struct Metal {
@@ -27,8 +27,9 @@ impl Material for Metal {
         let fuzzed_direction = scatter_direction + self.fuzz_factor * self.random_unit_vector();
 
         Some(Scatter {
-            ray: Ray::new(hit.position, fuzzed_direction),
+            ray: Ray::with_time(hit.position, fuzzed_direction, ray.time()),
             attenuation: self.albedo,
+            kind: RayKind::Specular,
         })
     }
 }
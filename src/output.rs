@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use image::{Rgb32FImage, ImageFormat, ImageResult, ImageError};
+use image::error::{UnsupportedError, ImageFormatHint};
+use image::codecs::hdr::HdrEncoder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    LowDynamicRange,
+    OpenExr,
+    Pfm,
+    RadianceHdr,
+}
+
+impl OutputFormat {
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("exr") => OutputFormat::OpenExr,
+            Some("pfm") => OutputFormat::Pfm,
+            Some("hdr") => OutputFormat::RadianceHdr,
+            _ => OutputFormat::LowDynamicRange,    // Let the image crate figure out the LDR format
+        }
+    }
+
+    pub fn is_high_dynamic_range(&self) -> bool {
+        !matches!(self, OutputFormat::LowDynamicRange)
+    }
+}
+
+/// Insert `_<suffix>` between the file name and its extension
+pub fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+
+    let file_name = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{}_{}.{}", stem, suffix, extension),
+        None => format!("{}_{}", stem, suffix),
+    };
+
+    path.with_file_name(file_name)
+}
+
+/// Write linear radiance, low dynamic range formats are rejected as they need tone mapping first
+pub fn write_high_dynamic_range(image: &Rgb32FImage, format: OutputFormat, path: &Path) -> ImageResult<()> {
+    match format {
+        OutputFormat::OpenExr => write_open_exr(image, path),
+        OutputFormat::Pfm => write_pfm(image, path),
+        OutputFormat::RadianceHdr => write_radiance_hdr(image, path),
+        OutputFormat::LowDynamicRange => Err(ImageError::Unsupported(UnsupportedError::from(ImageFormatHint::PathExtension(
+            path.extension().unwrap_or_default().into()
+        )))),
+    }
+}
+
+pub fn write_open_exr(image: &Rgb32FImage, path: &Path) -> ImageResult<()> {
+    image.save_with_format(path, ImageFormat::OpenExr)
+}
+
+pub fn write_radiance_hdr(image: &Rgb32FImage, path: &Path) -> ImageResult<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    let pixels: Vec<_> = image.pixels().copied().collect();
+
+    HdrEncoder::new(writer).encode(&pixels, image.width() as usize, image.height() as usize)
+}
+
+pub fn write_pfm(image: &Rgb32FImage, path: &Path) -> ImageResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    encode_pfm(image, &mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn encode_pfm<W: Write>(image: &Rgb32FImage, writer: &mut W) -> std::io::Result<()> {
+    // Negative scale marks the data as little endian
+    write!(writer, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+
+    // PFM scanlines are stored bottom to top
+    for y in (0..image.height()).rev() {
+        for x in 0..image.width() {
+            for channel in image.get_pixel(x, y).0 {
+                writer.write_all(&channel.to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(OutputFormat::from_path(Path::new("result.exr")), OutputFormat::OpenExr);
+        assert_eq!(OutputFormat::from_path(Path::new("result.PFM")), OutputFormat::Pfm);
+        assert_eq!(OutputFormat::from_path(Path::new("result.hdr")), OutputFormat::RadianceHdr);
+        assert_eq!(OutputFormat::from_path(Path::new("result.png")), OutputFormat::LowDynamicRange);
+        assert_eq!(OutputFormat::from_path(Path::new("result")), OutputFormat::LowDynamicRange);
+    }
+
+    #[test]
+    fn test_reject_low_dynamic_range() {
+        let image = Rgb32FImage::new(1, 1);
+        let result = write_high_dynamic_range(&image, OutputFormat::LowDynamicRange, Path::new("result.png"));
+
+        assert!(matches!(result, Err(ImageError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_encode_pfm() {
+        let mut image = Rgb32FImage::new(1, 2);
+        image.put_pixel(0, 0, Rgb([1.0, 2.0, 3.0]));
+        image.put_pixel(0, 1, Rgb([4.0, 5.0, 6.0]));
+
+        let mut data = Vec::new();
+        encode_pfm(&image, &mut data).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&data[..header.len()], header);
+
+        let values: Vec<f32> = data[header.len()..]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, vec![4.0, 5.0, 6.0, 1.0, 2.0, 3.0]);
+    }
+}
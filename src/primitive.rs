@@ -1,23 +1,82 @@
 pub mod sphere;
 pub mod plane;
+pub mod animated;
 
 use nalgebra_glm::Vec3;
 
 use crate::interval::Interval;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayKind};
 use crate::ray_hit::RayHit;
 use crate::material::Material;
+use crate::aabb::Aabb;
+use crate::light::area_light::AreaLightSampling;
+
+/// Point sampled on a primitive's surface, `pdf` is per unit solid angle as seen from the reference point
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSample {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub pdf: f32,
+}
+
+/// Which kinds of rays see a primitive, hidden primitives are skipped by those rays but still act normally otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Visibility {
+    pub camera: bool,
+    pub shadow: bool,       // Casts shadows
+    pub diffuse: bool,      // Seen in diffuse bounces
+    pub specular: bool,     // Seen in reflections
+    pub transmission: bool, // Seen through refractive materials
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility {
+            camera: true,
+            shadow: true,
+            diffuse: true,
+            specular: true,
+            transmission: true,
+        }
+    }
+}
+
+impl Visibility {
+    pub fn is_visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Shadow => self.shadow,
+            RayKind::Diffuse => self.diffuse,
+            RayKind::Specular => self.specular,
+            RayKind::Transmission => self.transmission,
+        }
+    }
+}
 
 pub trait Primitive {
-    fn normal(&self, location: &Vec3) -> Vec3;
+    /// Surface normal at `location`, `time` places animated primitives
+    fn normal(&self, location: &Vec3, time: f32) -> Vec3;
 
-    fn inverted_normal(&self, location: &Vec3) -> Vec3;
+    fn inverted_normal(&self, location: &Vec3, time: f32) -> Vec3;
 
     fn material(&self) -> &dyn Material;
+
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Area of a bounded surface, `None` for primitives that cannot be sampled as area lights
+    fn surface_area(&self) -> Option<f32> {
+        None
+    }
+
+    fn sample_surface(&self, _reference: &Vec3, _time: f32, _sampling: AreaLightSampling, _u: f32, _v: f32) -> Option<SurfaceSample> {
+        None
+    }
 }
 
 pub trait Hittable {
-    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit>;
+    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit<'_>>;
 }
 
 pub trait HittablePrimitive: Hittable + Primitive {}
@@ -0,0 +1,158 @@
+use nalgebra_glm::Vec3;
+
+use super::{Primitive, Hittable, HittablePrimitive, SurfaceSample};
+use crate::ray_hit::RayHit;
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::material::Material;
+use crate::animation::{Transform, TransformAnimation};
+use crate::light::area_light::AreaLightSampling;
+use crate::aabb::Aabb;
+
+/// Places a primitive, modelled in its own object space, in the world with an animated rigid transform
+pub struct AnimatedPrimitive {
+    primitive: Box<dyn HittablePrimitive + Sync>,
+    animation: TransformAnimation,
+}
+
+impl AnimatedPrimitive {
+    pub fn new(primitive: Box<dyn HittablePrimitive + Sync>, animation: TransformAnimation) -> Self {
+        AnimatedPrimitive {
+            primitive,
+            animation,
+        }
+    }
+}
+
+impl Primitive for AnimatedPrimitive {
+    fn normal(&self, location: &Vec3, time: f32) -> Vec3 {
+        let transform = self.animation.evaluate(time);
+        transform.transform_vector(&self.primitive.normal(&transform.inverse_transform_point(location), time))
+    }
+
+    fn inverted_normal(&self, location: &Vec3, time: f32) -> Vec3 {
+        -self.normal(location, time)
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.primitive.material()
+    }
+
+    /// Union of the bounds at every keyframe, rotations between keyframes may swing slightly past them
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.primitive.bounds()?;
+
+        self.animation.keyframe_transforms()
+            .map(|transform| transform_bounds(&bounds, transform))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    // Rigid transforms keep areas and solid angles, so only positions and normals need moving
+    fn surface_area(&self) -> Option<f32> {
+        self.primitive.surface_area()
+    }
+
+    fn sample_surface(&self, reference: &Vec3, time: f32, sampling: AreaLightSampling, u: f32, v: f32) -> Option<SurfaceSample> {
+        let transform = self.animation.evaluate(time);
+        let sample = self.primitive.sample_surface(&transform.inverse_transform_point(reference), time, sampling, u, v)?;
+
+        Some(SurfaceSample {
+            position: transform.transform_point(&sample.position),
+            normal: transform.transform_vector(&sample.normal),
+            pdf: sample.pdf,
+        })
+    }
+}
+
+impl Hittable for AnimatedPrimitive {
+    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit<'_>> {
+        let transform = self.animation.evaluate(ray.time());
+
+        // Rigid transforms preserve distances, so hit depths in object space are valid in world space
+        let object_ray = Ray::with_time(
+            transform.inverse_transform_point(ray.origin()),
+            transform.inverse_transform_vector(ray.direction()),
+            ray.time()
+        );
+
+        let mut hit = self.primitive.hit(&object_ray, interval)?;
+        hit.position = ray.at(hit.depth);
+        hit.normal = transform.transform_vector(&hit.normal);
+
+        Some(hit)
+    }
+}
+
+impl HittablePrimitive for AnimatedPrimitive {
+    //
+}
+
+/// Box around the corners of `bounds` after transforming them
+fn transform_bounds(bounds: &Aabb, transform: &Transform) -> Aabb {
+    let (min, max) = (bounds.min(), bounds.max());
+
+    (0..8)
+        .map(|corner| Vec3::new(
+            if corner & 1 == 0 { min.x } else { max.x },
+            if corner & 2 == 0 { min.y } else { max.y },
+            if corner & 4 == 0 { min.z } else { max.z },
+        ))
+        .map(|corner| Aabb::from_point(transform.transform_point(&corner)))
+        .reduce(|a, b| a.union(&b))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Transform;
+    use crate::primitive::sphere::Sphere;
+    use crate::material::diffuse::LambertianDiffuse;
+    use crate::material::emissive::Emissive;
+    use crate::primitive::plane::Rectangle;
+    use crate::light::area_light::AreaLight;
+
+    #[test]
+    fn test_moving_sphere() {
+        let sphere = Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0), 1.0,
+            Box::new(LambertianDiffuse::new(Vec3::new(0.0, 0.0, 0.0)))
+        );
+        let animated = AnimatedPrimitive::new(Box::new(sphere), TransformAnimation::linear(
+            0.0, Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
+            1.0, Transform::from_translation(Vec3::new(4.0, 0.0, 0.0))
+        ));
+
+        let interval = Interval::new(0.01, f32::MAX);
+        let early = Ray::with_time(Vec3::new(4.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let late = Ray::with_time(Vec3::new(4.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 1.0);
+
+        assert!(animated.hit(&early, &interval).is_none());
+
+        let hit = animated.hit(&late, &interval).unwrap();
+        assert!((hit.position - Vec3::new(4.0, 1.0, 0.0)).magnitude() < 1e-4);
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn test_rotating_emitter() {
+        // A downward facing light that turns to face +x over the animation
+        let rectangle = Rectangle::new(Vec3::zeros(), Vec3::new(0.0, -1.0, 0.0), 1.0, 1.0, Box::new(Emissive::new(Vec3::new(1.0, 1.0, 1.0), 1.0)));
+        let animated = AnimatedPrimitive::new(Box::new(rectangle), TransformAnimation::linear(
+            0.0, Transform::from_translation(Vec3::new(0.0, 2.0, 0.0)),
+            1.0, Transform::from_axis_angle(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 90.0)
+        ));
+
+        assert!(AreaLight::is_emitter(&animated));
+        let bounds = animated.bounds().unwrap();
+        assert!(bounds.min().x < -0.9 && bounds.max().x > 0.9 && bounds.max().y > 2.9);
+
+        // Normals and surface samples follow the ray time
+        assert!((animated.normal(&Vec3::new(0.0, 2.0, 0.0), 0.0) - Vec3::new(0.0, -1.0, 0.0)).magnitude() < 1e-4);
+        assert!((animated.normal(&Vec3::new(0.0, 2.0, 0.0), 1.0) - Vec3::new(1.0, 0.0, 0.0)).magnitude() < 1e-4);
+
+        let sample = animated.sample_surface(&Vec3::new(5.0, 2.0, 0.0), 1.0, AreaLightSampling::SolidAngle, 0.3, 0.7).unwrap();
+        assert!(f32::abs(sample.position.x) < 1e-4);
+        assert!((sample.normal - Vec3::new(1.0, 0.0, 0.0)).magnitude() < 1e-4);
+    }
+}
@@ -1,10 +1,13 @@
 use nalgebra_glm::Vec3;
 
-use super::{Primitive, Hittable, HittablePrimitive};
+use super::{Primitive, Hittable, HittablePrimitive, SurfaceSample};
 use crate::ray_hit::RayHit;
 use crate::interval::Interval;
 use crate::ray::Ray;
 use crate::material::Material;
+use crate::light::area_light::AreaLightSampling;
+use crate::sampling;
+use crate::aabb::Aabb;
 
 pub struct Plane {
     position: Vec3,
@@ -23,12 +26,12 @@ impl Plane {
 }
 
 impl Primitive for Plane {
-    fn normal(&self, _location: &Vec3) -> Vec3 {
+    fn normal(&self, _location: &Vec3, _time: f32) -> Vec3 {
         self.normal
     }
 
-    fn inverted_normal(&self, location: &Vec3) -> Vec3 {
-        -self.normal(location)
+    fn inverted_normal(&self, location: &Vec3, time: f32) -> Vec3 {
+        -self.normal(location, time)
     }
 
     fn material(&self) -> &dyn Material {
@@ -37,7 +40,7 @@ impl Primitive for Plane {
 }
 
 impl Hittable for Plane {
-    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit> {
+    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit<'_>> {
         let incident_angle = ray.direction().dot(&self.normal);
 
         if f32::abs(incident_angle) < 1e-8 {
@@ -77,22 +80,59 @@ impl Rectangle {
     }
 }
 
+impl Rectangle {
+    /// Half extents along the basis vectors, the hit test accepts |offset . basis| <= 1
+    fn half_extents(&self) -> (f32, f32) {
+        (1.0 / self.basis_vectors.0.magnitude(), 1.0 / self.basis_vectors.1.magnitude())
+    }
+}
+
 impl Primitive for Rectangle {
-    fn normal(&self, _location: &Vec3) -> Vec3 {
+    fn normal(&self, _location: &Vec3, _time: f32) -> Vec3 {
         self.normal
     }
 
-    fn inverted_normal(&self, location: &Vec3) -> Vec3 {
-        -self.normal(location)
+    fn inverted_normal(&self, location: &Vec3, time: f32) -> Vec3 {
+        -self.normal(location, time)
     }
 
     fn material(&self) -> &dyn Material {
         self.material.as_ref()
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (half_width, half_height) = self.half_extents();
+        let extent = (half_width * self.basis_vectors.0.normalize()).abs() + (half_height * self.basis_vectors.1.normalize()).abs();
+
+        Some(Aabb::new(self.position - extent, self.position + extent))
+    }
+
+    fn surface_area(&self) -> Option<f32> {
+        let (half_width, half_height) = self.half_extents();
+        Some(4.0 * half_width * half_height)
+    }
+
+    fn sample_surface(&self, reference: &Vec3, _time: f32, _sampling: AreaLightSampling, u: f32, v: f32) -> Option<SurfaceSample> {
+        // Solid angle sampling of a rectangle is not implemented, area samples are converted to solid angle instead
+        let (half_width, half_height) = self.half_extents();
+        let position = self.position
+            + (2.0 * u - 1.0) * half_width * self.basis_vectors.0.normalize()
+            + (2.0 * v - 1.0) * half_height * self.basis_vectors.1.normalize();
+
+        let to_sample = position - reference;
+        let distance = to_sample.magnitude();
+        let cos_light = f32::abs(self.normal.dot(&to_sample)) / distance;
+
+        Some(SurfaceSample {
+            position,
+            normal: self.normal,
+            pdf: sampling::area_to_solid_angle_pdf(1.0 / self.surface_area()?, distance, cos_light),
+        })
+    }
 }
 
 impl Hittable for Rectangle {
-    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit> {
+    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit<'_>> {
         let incident_angle = ray.direction().dot(&self.normal);
 
         if f32::abs(incident_angle) < 1e-8 {
@@ -1,10 +1,14 @@
+use std::f32::consts::PI;
 use nalgebra_glm::Vec3;
 
-use super::{Primitive, Hittable, HittablePrimitive};
+use super::{Primitive, Hittable, HittablePrimitive, SurfaceSample};
 use crate::ray_hit::RayHit;
 use crate::interval::Interval;
 use crate::ray::Ray;
 use crate::material::Material;
+use crate::light::area_light::AreaLightSampling;
+use crate::sampling;
+use crate::aabb::Aabb;
 
 pub struct Sphere {
     position: Vec3,
@@ -25,21 +29,66 @@ impl Sphere {
 }
 
 impl Primitive for Sphere {
-    fn normal(&self, location: &Vec3) -> Vec3 {
+    fn normal(&self, location: &Vec3, _time: f32) -> Vec3 {
         (location - self.position) / self.radius
     }
 
-    fn inverted_normal(&self, location: &Vec3) -> Vec3 {
-        -self.normal(location)
+    fn inverted_normal(&self, location: &Vec3, time: f32) -> Vec3 {
+        -self.normal(location, time)
     }
 
     fn material(&self) -> &dyn Material {
         self.material.as_ref()
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around(self.position, self.radius))
+    }
+
+    fn surface_area(&self) -> Option<f32> {
+        Some(4.0 * PI * self.radius_squared)
+    }
+
+    fn sample_surface(&self, reference: &Vec3, _time: f32, sampling: AreaLightSampling, u: f32, v: f32) -> Option<SurfaceSample> {
+        let radius = f32::abs(self.radius);
+        let to_center = self.position - reference;
+        let distance_squared = to_center.magnitude_squared();
+
+        // Inside the sphere every direction sees it, so only the outside can sample the visible cap
+        if let (AreaLightSampling::SolidAngle, true) = (sampling, distance_squared > self.radius_squared) {
+            let distance = f32::sqrt(distance_squared);
+            let axis = to_center / distance;
+            let cos_theta_max = f32::sqrt(1.0 - self.radius_squared / distance_squared);
+            let direction = sampling::uniform_cone(&axis, cos_theta_max, u, v);
+
+            // Closest intersection with the sphere, clamped for directions grazing the silhouette
+            let projection = direction.dot(&to_center);
+            let t = projection - f32::sqrt(f32::max(self.radius_squared - (distance_squared - projection * projection), 0.0));
+            let position = reference + t * direction;
+
+            return Some(SurfaceSample {
+                position,
+                normal: (position - self.position) / radius,
+                pdf: sampling::uniform_cone_pdf(cos_theta_max),
+            })
+        }
+
+        let normal = sampling::uniform_sphere(u, v);
+        let position = self.position + radius * normal;
+        let to_sample = position - reference;
+        let distance = to_sample.magnitude();
+        let cos_light = f32::abs(normal.dot(&to_sample)) / distance;
+
+        Some(SurfaceSample {
+            position,
+            normal,
+            pdf: sampling::area_to_solid_angle_pdf(1.0 / self.surface_area()?, distance, cos_light),
+        })
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit> {
+    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit<'_>> {
         let oc = ray.origin() - self.position;
         let a = ray.direction().magnitude_squared();
         let half_b = oc.dot(ray.direction());
@@ -97,6 +146,22 @@ mod tests {
         assert!(sphere.hit(&ray, &Interval::new(0.01, f32::MAX)).is_some())
     }
 
+    #[test]
+    fn test_sample_visible_cap() {
+        let sphere = Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0), 1.0,
+            Box::new(LambertianDiffuse::new(Vec3::new(0.0, 0.0, 0.0)))
+        );
+        let reference = Vec3::new(0.0, 0.0, 3.0);
+
+        for i in 0..10 {
+            let sample = sphere.sample_surface(&reference, 0.0, AreaLightSampling::SolidAngle, i as f32 / 10.0, 0.3).unwrap();
+
+            assert!(f32::abs(sample.position.magnitude() - 1.0) < 1e-4);
+            assert!(sample.normal.dot(&(reference - sample.position)) >= -1e-4);
+        }
+    }
+
     #[test]
     fn test_miss() {
         let sphere = Sphere::new(
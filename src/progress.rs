@@ -0,0 +1,96 @@
+use std::io::Write;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderProgress {
+    pub completed_tiles: usize,
+    pub total_tiles: usize,
+    pub completed_pixels: u64,
+    pub total_pixels: u64,
+    pub elapsed: Duration,
+}
+
+pub type ProgressCallback = Box<dyn Fn(&RenderProgress) + Send + Sync>;
+
+impl RenderProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.total_pixels == 0 {
+            return 1.0
+        }
+
+        self.completed_pixels as f32 / self.total_pixels as f32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed_tiles == self.total_tiles
+    }
+
+    /// Estimated time remaining, extrapolated from the pixel throughput so far
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction();
+        if fraction <= 0.0 {
+            return None
+        }
+
+        Some(self.elapsed.mul_f32((1.0 - fraction) / fraction))
+    }
+}
+
+/// Progress callback printing a single updating line with percentage and ETA to stdout
+pub fn terminal_progress() -> ProgressCallback {
+    Box::new(|progress: &RenderProgress| {
+        let eta = match progress.eta() {
+            Some(eta) => format!("{:.1}s", eta.as_secs_f32()),
+            None => String::from("-"),
+        };
+
+        let mut stdout = std::io::stdout().lock();
+        let _ = write!(
+            stdout,
+            "\rRendering: {:5.1}% ({}/{} tiles, ETA {})    ",
+            100.0 * progress.fraction(),
+            progress.completed_tiles,
+            progress.total_tiles,
+            eta
+        );
+
+        if progress.is_finished() {
+            let _ = writeln!(stdout);
+        }
+
+        let _ = stdout.flush();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta() {
+        let progress = RenderProgress {
+            completed_tiles: 1,
+            total_tiles: 4,
+            completed_pixels: 25,
+            total_pixels: 100,
+            elapsed: Duration::from_secs(10),
+        };
+
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+        assert!(!progress.is_finished());
+    }
+
+    #[test]
+    fn test_no_eta_before_start() {
+        let progress = RenderProgress {
+            completed_tiles: 0,
+            total_tiles: 4,
+            completed_pixels: 0,
+            total_pixels: 100,
+            elapsed: Duration::from_secs(1),
+        };
+
+        assert_eq!(progress.eta(), None);
+    }
+}
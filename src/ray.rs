@@ -4,13 +4,29 @@ use nalgebra_glm::Vec3;
 pub struct Ray {
     origin: Vec3,
     direction: Vec3,
+    time: f32,
+}
+
+/// Why a ray was traced, used to decide which primitives it can see
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayKind {
+    Camera,
+    Shadow,
+    Diffuse,
+    Specular,
+    Transmission,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Vec3, direction: Vec3, time: f32) -> Self {
         Ray {
             origin,
             direction,
+            time,
         }
     }
 
@@ -25,6 +41,10 @@ impl Ray {
     pub fn direction(&self) -> &Vec3 {
         &self.direction
     }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
 }
 
 #[cfg(test)]
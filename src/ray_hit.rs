@@ -15,6 +15,7 @@ pub struct RayHit<'primitive_lifetime> {
     pub position: Vec3,
     pub hit_type: HitType,
     pub normal: Vec3,
+    pub time: f32,
     pub material: &'primitive_lifetime dyn Material,
 }
 
@@ -23,12 +24,12 @@ impl<'a> RayHit<'a> {
     where
         P: Primitive
     {
-        let mut normal = primitive.normal(&position);
+        let mut normal = primitive.normal(&position, ray.time());
         let mut hit_type = HitType::FrontFace;
 
         // Dot product N * D is positive if vectors are aligned (i.e. the ray comes from inside the object!)
         if normal.dot(ray.direction()) > 0.0 {
-            normal = primitive.inverted_normal(&position);
+            normal = primitive.inverted_normal(&position, ray.time());
             hit_type = HitType::BackFace;
         }
 
@@ -37,6 +38,7 @@ impl<'a> RayHit<'a> {
             position,
             hit_type,
             normal,
+            time: ray.time(),
             material: primitive.material(),
         }
     }
@@ -49,6 +51,7 @@ impl<'a> std::fmt::Debug for RayHit<'a> {
             .field("position", &self.position)
             .field("hit_type", &self.hit_type)
             .field("normal", &self.normal)
+            .field("time", &self.time)
             .field("material", &"Dyn Material")
             .finish()
     }
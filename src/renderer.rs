@@ -1,129 +1,355 @@
+use std::f32::consts::PI;
 use std::path::Path;
+use std::time::{Duration, Instant};
 use nalgebra_glm::Vec3;
-use image::{RgbImage, Rgb};
+use rand::{thread_rng, Rng};
+use image::{imageops, ImageError, Rgb32FImage, RgbImage, Rgb};
 
 use crate::resolution::Resolution;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayKind};
 use crate::interval::Interval;
-use crate::camera::Camera;
-use crate::primitive::Hittable;
-use crate::material::MaterialTransparency;
+use crate::camera::{Camera, CameraSample};
+use crate::camera::stereo::{StereoEye, StereoLayout};
 use crate::scene::Scene;
+use crate::output::{self, OutputFormat};
+use crate::tonemap::ToneMapping;
+use crate::film::{Film, FilmTile, filter::ReconstructionFilter};
+use crate::tile::{self, Tile, TileOrder};
+use crate::crop::{CropWindow, CropOutput};
+use crate::progress::{ProgressCallback, RenderProgress};
+use crate::statistics::RenderStatistics;
+use crate::sequence::{Frame, FrameSequence};
+
+const TIMED_PIXEL_INTERVAL: u32 = 16;
 
 #[derive(Clone, Copy)]
 pub struct RendererConfig {
     pub resolution: Resolution,
     pub sample_count: u32,
     pub max_bounces: u32,
+    pub tone_mapping: ToneMapping,
+    pub filter: ReconstructionFilter,
+    pub tile_size: u32,
+    pub tile_order: TileOrder,
+    pub crop: Option<CropWindow>,
+    pub crop_output: CropOutput,
 }
 
 pub struct Renderer {
     config: RendererConfig,
-    render_target: RgbImage,
+    film: Film,
+    progress_callback: Option<ProgressCallback>,
+}
+
+/// Bookkeeping shared between tiles, updated once per finished tile
+struct RenderState<'film> {
+    film: &'film mut Film,
+    statistics: RenderStatistics,
+    progress: RenderProgress,
 }
 
 impl Renderer {
     pub fn new(config: RendererConfig) -> Self {
         Renderer {
             config,
-            render_target: RgbImage::new(config.resolution.width(), config.resolution.height()),
+            film: Film::new(config.resolution, config.filter),
+            progress_callback: None,
         }
     }
 
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
+
     #[cfg(feature = "parallel")]
-    pub fn render(&mut self, camera: &Camera, scene: &Scene) {
+    pub fn render(&mut self, camera: &(dyn Camera + Sync), scene: &Scene) -> RenderStatistics {
         use rayon::prelude::*;
 
-        let z_interval = camera.scene_depth_interval();
-        let (width, height) = self.config.resolution.dimensions();
+        let start = Instant::now();
+        let region = self.render_region();
+        let tiles = tile::generate_tiles(&region, self.config.tile_size, self.config.tile_order);
+        let regions = camera.film_regions();
+        self.film.set_regions(regions.clone());
+
+        // Tiles are rendered into their own film tile, the shared film is only locked to merge a finished tile
+        let config = &self.config;
+        let progress_callback = &self.progress_callback;
+        let state = std::sync::Mutex::new(RenderState::new(&mut self.film, &region, tiles.len()));
+        tiles.into_par_iter().for_each(|tile| {
+            let film_tile = FilmTile::new(&tile, &config.resolution, &config.filter, &regions);
+            let (film_tile, statistics) = Self::render_tile(config, &tile, film_tile, camera, scene);
 
-        let one_over_sample_count = 1.0 / self.config.sample_count as f32;
-        let render_output = std::sync::Mutex::new(&mut self.render_target);
-        (0..height).into_par_iter().for_each(|y| {
-            for x in 0..width {
-                let mut sample_color = Vec3::zeros();
+            let progress = state.lock().unwrap().merge_tile(&tile, film_tile, &statistics, start);
+            if let Some(callback) = progress_callback {
+                callback(&progress);
+            }
+        });
 
-                for sample in 0..self.config.sample_count {
-                    let ray = camera.get_primary_ray(x, y, sample);
-                    let color = Self::bounce_ray(&ray, scene, z_interval, self.config.max_bounces);
+        let mut statistics = state.into_inner().unwrap().statistics;
+        statistics.render_time = start.elapsed();
+        statistics
+    }
 
-                    sample_color += color;
-                }
-    
-                let color = Self::rgb_to_gamma(sample_color * one_over_sample_count);
-                let color = Self::vec3_to_color(color);
-                
-                render_output.lock().unwrap().put_pixel(x, y, color);
+    #[cfg(feature = "single_threaded")]
+    pub fn render(&mut self, camera: &(dyn Camera + Sync), scene: &Scene) -> RenderStatistics {
+        let start = Instant::now();
+        let region = self.render_region();
+        let tiles = tile::generate_tiles(&region, self.config.tile_size, self.config.tile_order);
+        self.film.set_regions(camera.film_regions());
+
+        let mut state = RenderState::new(&mut self.film, &region, tiles.len());
+        for tile in tiles {
+            let film_tile = state.film.create_tile(&tile);
+            let (film_tile, statistics) = Self::render_tile(&self.config, &tile, film_tile, camera, scene);
+
+            let progress = state.merge_tile(&tile, film_tile, &statistics, start);
+            if let Some(callback) = &self.progress_callback {
+                callback(&progress);
             }
+        }
+
+        let mut statistics = state.statistics;
+        statistics.render_time = start.elapsed();
+        statistics
+    }
+
+    /// Render and save every frame of `sequence` as numbered files next to `path`.
+    /// `camera_at` builds the camera for each frame, open its shutter over `frame.shutter` to animate objects
+    pub fn render_sequence<F>(&mut self, sequence: &FrameSequence, scene: &Scene, path: &Path, mut camera_at: F) -> std::io::Result<RenderStatistics>
+    where
+        F: FnMut(&Frame) -> Box<dyn Camera + Sync>
+    {
+        let start = Instant::now();
+        let mut statistics = RenderStatistics::default();
+
+        if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        for frame in sequence.frames() {
+            self.film = Film::new(self.config.resolution, self.config.filter);
+
+            let camera = camera_at(&frame);
+            statistics.merge(&self.render(camera.as_ref(), scene));
+            self.try_save_render(&sequence.frame_path(path, frame.number))?;
+        }
+
+        statistics.render_time = start.elapsed();
+        Ok(statistics)
+    }
+
+    /// Panics when the image cannot be written, see `try_save_render`
+    pub fn save_render(&self, path: &Path) {
+        self.try_save_render(path).expect("Failed to save output image");
+    }
+
+    /// Save the render, a crop window covering no pixels is an `InvalidInput` error as there is nothing to write
+    pub fn try_save_render(&self, path: &Path) -> std::io::Result<()> {
+        let image = self.crop_image(&self.film.to_image(), &Tile::from_resolution(&self.config.resolution));
+        if image.width() == 0 || image.height() == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "crop window covers no pixels"))
+        }
+
+        self.write_image(&image, path)
+    }
+
+    /// Save a stereo render, `SeparateFiles` writes `<name>_left` and `<name>_right` next to `path`
+    pub fn save_stereo_render(&self, path: &Path, layout: StereoLayout) {
+        if layout != StereoLayout::SeparateFiles {
+            return self.save_render(path)
+        }
+
+        // Eyes split the full frame, the crop window is then applied to each eye
+        let image = self.film.to_image();
+        let eye_resolution = Resolution::new(self.config.resolution.width() / 2, self.config.resolution.height());
+
+        for (eye, name) in [(StereoEye::Left, "left"), (StereoEye::Right, "right")] {
+            let eye_image = self.crop_image(&image, &layout.eye_bounds(eye, &eye_resolution));
+
+            // A crop window entirely inside the other eye leaves nothing to save
+            if eye_image.width() > 0 && eye_image.height() > 0 {
+                self.write_image(&eye_image, &output::path_with_suffix(path, name)).expect("Failed to save output image");
+            }
+        }
+    }
+
+    fn write_image(&self, image: &Rgb32FImage, path: &Path) -> std::io::Result<()> {
+        let format = OutputFormat::from_path(path);
+
+        let result = if format.is_high_dynamic_range() {
+            output::write_high_dynamic_range(image, format, path)
+        }
+        else {
+            self.to_low_dynamic_range(image).save(path)
+        };
+
+        result.map_err(|error| match error {
+            ImageError::IoError(error) => error,
+            error => std::io::Error::other(error),
         })
     }
 
-    #[cfg(feature = "single_threaded")]
-    pub fn render(&mut self, camera: &Camera, scene: &Scene) {
+    /// Pixel region that is traced, the crop window if one is set
+    pub fn render_region(&self) -> Tile {
+        match &self.config.crop {
+            Some(crop) => crop.pixel_bounds(&self.config.resolution),
+            None => Tile::from_resolution(&self.config.resolution),
+        }
+    }
+
+    /// Cut `frame` out of the film image and apply the crop window to it
+    fn crop_image(&self, image: &Rgb32FImage, frame: &Tile) -> Rgb32FImage {
+        if self.config.crop.is_none() {
+            return imageops::crop_imm(image, frame.x, frame.y, frame.width, frame.height).to_image()
+        }
+
+        let region = self.render_region().intersect(frame);
+        let cropped = imageops::crop_imm(image, region.x, region.y, region.width, region.height).to_image();
+
+        match self.config.crop_output {
+            CropOutput::CropOnly => cropped,
+            CropOutput::FullFrame => {
+                // Filters splat past the crop edge, so only keep the pixels inside the window
+                let mut full_frame = Rgb32FImage::new(frame.width, frame.height);
+                imageops::replace(&mut full_frame, &cropped, (region.x - frame.x) as i64, (region.y - frame.y) as i64);
+                full_frame
+            },
+        }
+    }
+
+    fn to_low_dynamic_range(&self, image: &Rgb32FImage) -> RgbImage {
+        RgbImage::from_fn(image.width(), image.height(), |x, y| {
+            let Rgb([r, g, b]) = *image.get_pixel(x, y);
+
+            let color = self.config.tone_mapping.apply(Vec3::new(r, g, b));
+            Self::vec3_to_color(color)
+        })
+    }
+
+    fn render_tile(config: &RendererConfig, tile: &Tile, mut film_tile: FilmTile, camera: &(dyn Camera + Sync), scene: &Scene) -> (FilmTile, RenderStatistics) {
+        let start = Instant::now();
         let z_interval = camera.scene_depth_interval();
-        let (width, height) = self.config.resolution.dimensions();
+        let shutter = camera.shutter_interval();
+        let mut statistics = RenderStatistics::default();
 
-        for y in 0..height {
-            for x in 0..width {
-                let mut sample_sum_color = Vec3::zeros();
+        // Reading the clock around every intersection costs more than it measures, so only some pixels are timed
+        let mut pixel_count = 0;
+        let mut timed_pixels = 0;
 
-                for sample in 0..self.config.sample_count {
-                    let ray = camera.get_primary_ray(x, y, sample);
-                    let color = Self::bounce_ray(&ray, scene, z_interval, self.config.max_bounces);
+        for y in tile.y..(tile.y + tile.height) {
+            for x in tile.x..(tile.x + tile.width) {
+                let timed = pixel_count % TIMED_PIXEL_INTERVAL == 0;
+                pixel_count += 1;
+                if timed {
+                    timed_pixels += 1;
+                }
+
+                for _sample in 0..config.sample_count {
+                    let (film_x, film_y) = Self::sample_film_position(x, y);
+                    let sample = CameraSample { film_x, film_y, time: Self::sample_time(&shutter) };
+                    let color = match camera.generate_ray(&sample) {
+                        Some(camera_ray) => {
+                            statistics.primary_rays += 1;
+                            camera_ray.weight * Self::bounce_ray(&camera_ray.ray, scene, z_interval, config.max_bounces, RayKind::Camera, false, timed, &mut statistics)
+                        },
+                        None => Vec3::zeros(),
+                    };
 
-                    sample_sum_color += color;
+                    film_tile.add_sample(film_x, film_y, color);
                 }
-    
-                let color = sample_sum_color / self.config.sample_count as f32;
-                let color = Self::rgb_to_gamma(color);
-                let color = Self::vec3_to_color(color);
-                self.render_target.put_pixel(x, y, color);
             }
         }
-    }
 
-    pub fn save_render(&self, path: &Path) {
-        self.render_target.save(path).expect("Failed to save output image");
+        // Scale the timed pixels up to the whole tile
+        let tile_time = start.elapsed();
+        if timed_pixels > 0 {
+            statistics.intersection_time = Duration::min(statistics.intersection_time.mul_f64(pixel_count as f64 / timed_pixels as f64), tile_time);
+        }
+
+        statistics.shading_time = tile_time.saturating_sub(statistics.intersection_time);
+        (film_tile, statistics)
     }
 
-    fn bounce_ray(ray: &Ray, scene: &Scene, z_interval: &Interval, depth: u32) -> Vec3 {
+    /// Radiance arriving along `ray`. `lights_sampled` marks rays leaving a surface whose direct light was already
+    /// sampled, those skip the area lights and environment so their light is not counted twice.
+    /// Intersection time is only measured for `timed` paths
+    #[allow(clippy::too_many_arguments)]
+    fn bounce_ray(ray: &Ray, scene: &Scene, z_interval: &Interval, depth: u32, kind: RayKind, lights_sampled: bool, timed: bool, statistics: &mut RenderStatistics) -> Vec3 {
         if depth == 0 {
             return Vec3::zeros();
         }
 
-        let closest_hit = scene.hit(ray, z_interval);
+        let intersection_start = timed.then(Instant::now);
+        let closest_hit = scene.trace(ray, z_interval, kind, statistics);
+        if let Some(intersection_start) = intersection_start {
+            statistics.intersection_time += intersection_start.elapsed();
+        }
+
         match closest_hit {
-            Some(hit) => {
+            Some((primitive_index, hit)) => {
+                statistics.path_vertices += 1;
                 let scatter = hit.material.scatter(ray, &hit);
 
                 match scatter {
                     Some(scatter) => {
-                        let object_color = scatter.attenuation.component_mul(
-                            &Self::bounce_ray(&scatter.ray, scene, z_interval, depth - 1)
-                        );
+                        if depth > 1 {
+                            statistics.secondary_rays += 1;
+                        }
 
-                        match hit.material.material_transparency() {
-                            MaterialTransparency::Opaque => object_color.component_mul(&scene.shadow_ray(&hit, z_interval)),
-                            MaterialTransparency::Transparent => object_color,
+                        // Lambertian surfaces get their direct light from the lights, the scattered ray adds the rest
+                        let diffuse_albedo = hit.material.diffuse_albedo();
+                        let samples_lights = diffuse_albedo != Vec3::zeros();
+                        let direct = if samples_lights {
+                            let intersection_start = timed.then(Instant::now);
+                            let irradiance = scene.shadow_ray(&hit, primitive_index, z_interval, statistics);
+                            if let Some(intersection_start) = intersection_start {
+                                statistics.intersection_time += intersection_start.elapsed();
+                            }
+
+                            diffuse_albedo.component_mul(&irradiance) / PI
                         }
+                        else {
+                            Vec3::zeros()
+                        };
+
+                        let indirect = scatter.attenuation.component_mul(
+                            &Self::bounce_ray(&scatter.ray, scene, z_interval, depth - 1, scatter.kind, samples_lights, timed, statistics)
+                        );
+
+                        direct + indirect
                     },
                     None => {
+                        if lights_sampled && scene.is_area_light(primitive_index) {
+                            return Vec3::zeros()
+                        }
+
                         hit.material.emit()
                     },
                 }
             }
             None => {
-                scene.get_sky_color(ray)
+                if lights_sampled && scene.has_environment() {
+                    return Vec3::zeros()
+                }
+
+                scene.get_sky_color(ray, kind == RayKind::Camera)
             }
         }
     }
 
-    fn rgb_to_gamma(color: Vec3) -> Vec3 {
-        Vec3::new(
-            f32::sqrt(color.x),
-            f32::sqrt(color.y),
-            f32::sqrt(color.z),
-        )
+    fn sample_film_position(x: u32, y: u32) -> (f32, f32) {
+        // Sampling is random for now -> use stratified samples for consistent sampling
+        let mut rng = thread_rng();
+
+        (x as f32 + rng.gen_range(0.0..1.0), y as f32 + rng.gen_range(0.0..1.0))
+    }
+
+    fn sample_time(shutter: &Interval) -> f32 {
+        if shutter.max() <= shutter.min() {
+            return shutter.min()
+        }
+
+        thread_rng().gen_range(shutter.min()..shutter.max())
     }
 
     fn vec3_to_color(color: Vec3) -> Rgb<u8> {
@@ -136,3 +362,127 @@ impl Renderer {
         ])
     }
 }
+
+impl<'film> RenderState<'film> {
+    fn new(film: &'film mut Film, region: &Tile, total_tiles: usize) -> Self {
+        RenderState {
+            film,
+            statistics: RenderStatistics::default(),
+            progress: RenderProgress {
+                completed_tiles: 0,
+                total_tiles,
+                completed_pixels: 0,
+                total_pixels: region.pixel_count() as u64,
+                elapsed: Duration::ZERO,
+            },
+        }
+    }
+
+    fn merge_tile(&mut self, tile: &Tile, film_tile: FilmTile, statistics: &RenderStatistics, start: Instant) -> RenderProgress {
+        self.film.merge_tile(film_tile);
+        self.statistics.merge(statistics);
+
+        self.progress.completed_tiles += 1;
+        self.progress.completed_pixels += tile.pixel_count() as u64;
+        self.progress.elapsed = start.elapsed();
+        self.progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::plane::{Plane, Rectangle};
+    use crate::material::diffuse::LambertianDiffuse;
+    use crate::material::emissive::Emissive;
+    use crate::scene::SkyAttenuation;
+    use crate::camera::{CameraOrientation, FocusMode, perspective::PerspectiveCamera};
+
+    fn test_config(resolution: Resolution) -> RendererConfig {
+        RendererConfig {
+            resolution,
+            sample_count: 1,
+            max_bounces: 1,
+            tone_mapping: ToneMapping::default(),
+            filter: ReconstructionFilter::default(),
+            tile_size: 4,
+            tile_order: TileOrder::Scanline,
+            crop: None,
+            crop_output: CropOutput::CropOnly,
+        }
+    }
+
+    #[test]
+    fn test_sequence_reports_write_errors() {
+        let resolution = Resolution::new(4, 4);
+        let mut renderer = Renderer::new(test_config(resolution));
+        let scene = Scene::new(SkyAttenuation { light_color: Vec3::zeros(), sky_color: Vec3::zeros() }, vec![], vec![]);
+        let sequence = FrameSequence::new(1, 1, 24.0).unwrap();
+
+        // A directory in place of the first frame makes writing it fail
+        let directory = std::env::temp_dir().join(format!("raytracer_sequence_{}", std::process::id()));
+        let path = directory.join("frame.png");
+        std::fs::create_dir_all(sequence.frame_path(&path, 1)).unwrap();
+
+        let result = renderer.render_sequence(&sequence, &scene, &path, |_| {
+            Box::new(PerspectiveCamera::new(
+                Vec3::new(0.0, 0.0, 1.0), CameraOrientation::look_at(Vec3::zeros()), 60.0,
+                FocusMode::AutoFocus, 0.0, Interval::new(0.001, 100.0), &resolution
+            ).unwrap())
+        });
+
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_reject_empty_crop() {
+        let config = RendererConfig {
+            crop: Some(CropWindow::Pixels { x: 2, y: 2, width: 0, height: 2 }),
+            ..test_config(Resolution::new(4, 4))
+        };
+        let mut renderer = Renderer::new(config);
+        let scene = Scene::new(SkyAttenuation { light_color: Vec3::zeros(), sky_color: Vec3::zeros() }, vec![], vec![]);
+        let camera = PerspectiveCamera::new(
+            Vec3::new(0.0, 0.0, 1.0), CameraOrientation::look_at(Vec3::zeros()), 60.0,
+            FocusMode::AutoFocus, 0.0, Interval::new(0.001, 100.0), &config.resolution
+        ).unwrap();
+        renderer.render(&camera, &scene);
+
+        let path = std::env::temp_dir().join(format!("raytracer_empty_crop_{}.png", std::process::id()));
+
+        let error = renderer.try_save_render(&path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_area_light_sampling_converges_to_bounces() {
+        // A diffuse floor under a rectangular emitter, in an otherwise black world
+        let scene = || Scene::new(
+            SkyAttenuation { light_color: Vec3::zeros(), sky_color: Vec3::zeros() },
+            vec![
+                Box::new(Plane::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), Box::new(LambertianDiffuse::new(Vec3::new(0.5, 0.5, 0.5))))),
+                Box::new(Rectangle::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 1.0, 1.0, Box::new(Emissive::new(Vec3::new(1.0, 1.0, 1.0), 2.0)))),
+            ],
+            vec![],
+        );
+
+        let estimate = |scene: &Scene| {
+            let ray = Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+            let interval = Interval::new(0.001, f32::INFINITY);
+            let mut statistics = RenderStatistics::default();
+
+            let count = 40000;
+            let total: f32 = (0..count)
+                .map(|_| Renderer::bounce_ray(&ray, scene, &interval, 2, RayKind::Camera, false, false, &mut statistics).x)
+                .sum();
+            total / count as f32
+        };
+
+        let sampled = estimate(&scene());
+        let bounced = estimate(&scene().without_area_lights());
+        assert!(bounced > 0.0);
+        assert!(f32::abs(sampled - bounced) < 0.05 * bounced, "area light sampling estimated {} instead of {}", sampled, bounced);
+    }
+}
@@ -0,0 +1,208 @@
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+
+/// Piecewise constant 1D distribution for importance sampling discrete values by weight
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    function: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    pub fn new(function: Vec<f32>) -> Self {
+        assert!(!function.is_empty(), "Distribution needs at least one value");
+
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.0);
+        for value in &function {
+            cdf.push(cdf[cdf.len() - 1] + f32::max(*value, 0.0));
+        }
+
+        let integral = cdf[function.len()];
+        if integral > 0.0 {
+            cdf.iter_mut().for_each(|c| *c /= integral);
+        }
+        else {
+            // Degenerate function, fall back to uniform sampling
+            let count = function.len() as f32;
+            cdf.iter_mut().enumerate().for_each(|(i, c)| *c = i as f32 / count);
+        }
+
+        Distribution1D {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.function.len()
+    }
+
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Pick an index for a uniform sample `u` in [0, 1), returns the index, its probability and the
+    /// remapped position of `u` within the chosen bucket so it can be reused as a fresh sample
+    pub fn sample_discrete(&self, u: f32) -> (usize, f32, f32) {
+        let index = usize::min(self.cdf.partition_point(|&c| c <= u), self.count()).saturating_sub(1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let remapped = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+
+        (index, self.pmf(index), f32::clamp(remapped, 0.0, 1.0 - f32::EPSILON))
+    }
+
+    pub fn pmf(&self, index: usize) -> f32 {
+        self.cdf[index + 1] - self.cdf[index]
+    }
+
+    /// Sample a position in [0, 1) treating the values as a piecewise constant function, returns the position and its density
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32) {
+        let (index, pmf, remapped) = self.sample_discrete(u);
+        let count = self.count() as f32;
+
+        ((index as f32 + remapped) / count, pmf * count)
+    }
+
+    pub fn pdf(&self, x: f32) -> f32 {
+        let index = usize::min((x * self.count() as f32) as usize, self.count() - 1);
+        self.pmf(index) * self.count() as f32
+    }
+}
+
+/// Piecewise constant 2D distribution over [0, 1)^2, sampled by row and then by column within the row
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `function` holds `height` rows of `width` values
+    pub fn new(width: usize, height: usize, function: &[f32]) -> Self {
+        assert_eq!(function.len(), width * height, "Distribution size does not match its dimensions");
+
+        let conditional: Vec<Distribution1D> = function.chunks_exact(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|row| row.integral()).collect());
+
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Returns the sampled (x, y) position and its density
+    pub fn sample_continuous(&self, u: f32, v: f32) -> ((f32, f32), f32) {
+        let (y, pdf_y) = self.marginal.sample_continuous(v);
+        let row = usize::min((y * self.conditional.len() as f32) as usize, self.conditional.len() - 1);
+        let (x, pdf_x) = self.conditional[row].sample_continuous(u);
+
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, x: f32, y: f32) -> f32 {
+        let row = usize::min((y * self.conditional.len() as f32) as usize, self.conditional.len() - 1);
+        self.marginal.pdf(y) * self.conditional[row].pdf(x)
+    }
+}
+
+/// Uniformly distributed direction on the unit sphere
+pub fn uniform_sphere(u: f32, v: f32) -> Vec3 {
+    let z = 1.0 - 2.0 * u;
+    let r = f32::sqrt(f32::max(1.0 - z * z, 0.0));
+    let phi = 2.0 * PI * v;
+
+    Vec3::new(r * f32::cos(phi), r * f32::sin(phi), z)
+}
+
+/// Two unit vectors perpendicular to `normal` and to each other
+pub fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let helper = if f32::abs(normal.x) > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let tangent = normal.cross(&helper).normalize();
+
+    (tangent, normal.cross(&tangent))
+}
+
+/// Uniformly distributed direction within the cone around `axis` whose half angle has cosine `cos_theta_max`
+pub fn uniform_cone(axis: &Vec3, cos_theta_max: f32, u: f32, v: f32) -> Vec3 {
+    let cos_theta = 1.0 - u * (1.0 - cos_theta_max);
+    let sin_theta = f32::sqrt(f32::max(1.0 - cos_theta * cos_theta, 0.0));
+    let phi = 2.0 * PI * v;
+
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    (sin_theta * f32::cos(phi)) * tangent + (sin_theta * f32::sin(phi)) * bitangent + cos_theta * axis
+}
+
+/// Density of `uniform_cone` per unit solid angle
+pub fn uniform_cone_pdf(cos_theta_max: f32) -> f32 {
+    1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+}
+
+/// Convert a density per unit area on a surface to a density per unit solid angle as seen from `distance` away
+pub fn area_to_solid_angle_pdf(area_pdf: f32, distance: f32, cos_surface: f32) -> f32 {
+    if cos_surface <= 0.0 {
+        return 0.0
+    }
+
+    area_pdf * distance * distance / cos_surface
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_by_weight() {
+        let distribution = Distribution1D::new(vec![1.0, 0.0, 3.0]);
+
+        assert_eq!(distribution.integral(), 4.0);
+        assert_eq!(distribution.sample_discrete(0.1).0, 0);
+        assert_eq!(distribution.sample_discrete(0.25).0, 2);
+        assert_eq!(distribution.sample_discrete(0.99).0, 2);
+        assert_eq!(distribution.pmf(1), 0.0);
+        assert_eq!(distribution.pmf(2), 0.75);
+
+        let (_, _, remapped) = distribution.sample_discrete(0.625);
+        assert!(f32::abs(remapped - 0.5) < 1e-6);
+    }
+
+    #[test]
+    fn test_all_zero_is_uniform() {
+        let distribution = Distribution1D::new(vec![0.0, 0.0]);
+
+        assert_eq!(distribution.sample_discrete(0.3).0, 0);
+        assert_eq!(distribution.sample_discrete(0.7).0, 1);
+        assert_eq!(distribution.pmf(0), 0.5);
+    }
+
+    #[test]
+    fn test_2d_distribution() {
+        // Only the bottom right cell has weight
+        let distribution = Distribution2D::new(2, 2, &[0.0, 0.0, 0.0, 1.0]);
+
+        let ((x, y), pdf) = distribution.sample_continuous(0.3, 0.7);
+        assert!(x >= 0.5 && y >= 0.5);
+        assert_eq!(pdf, 4.0);
+        assert_eq!(distribution.pdf(0.75, 0.75), 4.0);
+        assert_eq!(distribution.pdf(0.25, 0.75), 0.0);
+    }
+
+    #[test]
+    fn test_uniform_cone() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let cos_theta_max = f32::cos(f32::to_radians(10.0));
+
+        for i in 0..10 {
+            for j in 0..10 {
+                let direction = uniform_cone(&axis, cos_theta_max, i as f32 / 9.0, j as f32 / 10.0);
+
+                assert!(f32::abs(direction.magnitude() - 1.0) < 1e-5);
+                assert!(direction.dot(&axis) >= cos_theta_max - 1e-5);
+            }
+        }
+    }
+}
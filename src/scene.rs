@@ -1,93 +1,442 @@
+use rand::{thread_rng, Rng};
 use nalgebra_glm::Vec3;
 
 use crate::interval::Interval;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayKind};
 use crate::ray_hit::RayHit;
-use crate::primitive::{HittablePrimitive, Hittable};
+use crate::primitive::{HittablePrimitive, Hittable, Visibility};
 use crate::material::MaterialTransparency;
-use crate::light::Light;
+use crate::light::{Light, LightSample, LightLink};
+use crate::light::area_light::{AreaLight, AreaLightSampling};
+use crate::light::environment_light::EnvironmentLight;
+use crate::light::light_bvh::{LightBvh, LightBounds};
+use crate::sampling::Distribution1D;
+use crate::statistics::RenderStatistics;
 
 pub struct SkyAttenuation {
     pub light_color: Vec3,
     pub sky_color: Vec3,
 }
 
+/// How local lights are picked for direct lighting, lights at infinity are always sampled
+#[derive(Debug, Clone, Copy)]
+pub enum LightSelection {
+    All,                    // Trace a shadow ray to every light, cost grows with the light count
+    Power { samples: u32 }, // Pick lights proportionally to their power
+    Bvh { samples: u32 },   // Pick lights through the light hierarchy, favouring bright lights close to the point
+}
+
+/// Light with a position, either from the light list or an emissive primitive
+#[derive(Debug, Clone, Copy)]
+enum LocalLight {
+    Light(usize),
+    Area(usize),
+}
+
 pub struct Scene {
     sky_attenuation: SkyAttenuation,
     primitives: Vec<Box<dyn HittablePrimitive + Sync>>,
     lights: Vec<Box<dyn Light + Sync>>,
+    area_lights: Vec<AreaLight>,
+    area_light_sampling: AreaLightSampling,
+    environment: Option<EnvironmentLight>,
+    light_selection: LightSelection,
+    infinite_lights: Vec<usize>,
+    local_lights: Vec<LocalLight>,
+    power_distribution: Option<Distribution1D>,
+    light_bvh: LightBvh,
+    visibility: Vec<Visibility>,
+    light_links: Vec<LightLink>,
+    area_light_links: Vec<LightLink>,
 }
 
 impl Scene {
     pub fn new(sky_attenuation: SkyAttenuation, primitives: Vec<Box<dyn HittablePrimitive + Sync>>, lights: Vec<Box<dyn Light + Sync>>) -> Self {
-        Scene {
+        // Emissive primitives are sampled directly instead of relying on bounces to find them
+        let area_lights: Vec<AreaLight> = primitives.iter()
+            .enumerate()
+            .filter(|(_, primitive)| AreaLight::is_emitter(primitive.as_ref()))
+            .map(|(index, _)| AreaLight::new(index))
+            .collect();
+
+        let mut scene = Scene {
             sky_attenuation,
+            visibility: vec![Visibility::default(); primitives.len()],
+            light_links: vec![LightLink::All; lights.len()],
+            area_light_links: vec![LightLink::All; area_lights.len()],
             primitives,
             lights,
+            area_lights,
+            area_light_sampling: AreaLightSampling::SolidAngle,
+            environment: None,
+            light_selection: LightSelection::All,
+            infinite_lights: Vec::new(),
+            local_lights: Vec::new(),
+            power_distribution: None,
+            light_bvh: LightBvh::new(&[]),
+        };
+
+        scene.index_lights();
+        scene
+    }
+
+    /// Sort lights into lights at infinity and local lights, and build the structures to pick local lights with
+    fn index_lights(&mut self) {
+        self.infinite_lights = (0..self.lights.len()).filter(|&index| self.lights[index].bounds().is_none()).collect();
+
+        let mut local_lights = Vec::new();
+        let mut light_bounds = Vec::new();
+        for (index, light) in self.lights.iter().enumerate() {
+            if let Some(bounds) = light.bounds() {
+                local_lights.push(LocalLight::Light(index));
+                light_bounds.push(LightBounds { bounds, power: light.power() });
+            }
+        }
+        for (index, area_light) in self.area_lights.iter().enumerate() {
+            if !self.samples_area_light(area_light) {
+                continue;
+            }
+
+            if let Some(bounds) = AreaLight::light_bounds(self.primitives[area_light.primitive_index()].as_ref()) {
+                local_lights.push(LocalLight::Area(index));
+                light_bounds.push(bounds);
+            }
+        }
+
+        self.power_distribution = if light_bounds.is_empty() {
+            None
         }
+        else {
+            Some(Distribution1D::new(light_bounds.iter().map(|light| light.power).collect()))
+        };
+        self.local_lights = local_lights;
+        self.light_bvh = LightBvh::new(&light_bounds);
     }
 
-    pub fn get_sky_color(&self, ray: &Ray) -> Vec3 {
-        let a = 0.5 * (ray.direction().y + 1.0);
+    pub fn with_light_selection(mut self, light_selection: LightSelection) -> Self {
+        self.light_selection = light_selection;
+        self
+    }
 
-        (1.0 - a) * self.sky_attenuation.light_color + a * self.sky_attenuation.sky_color
+    /// Set which rays see the primitive at `primitive_index`, an index past the primitives is ignored
+    pub fn with_visibility(mut self, primitive_index: usize, visibility: Visibility) -> Self {
+        if let Some(primitive_visibility) = self.visibility.get_mut(primitive_index) {
+            *primitive_visibility = visibility;
+            self.index_lights();
+        }
+
+        self
     }
 
-    pub fn shadow_ray(&self, hit: &RayHit, interval: &Interval) -> Vec3 {
-        let mut combined_light = Vec3::zeros();
+    /// Restrict which primitives the light at `light_index` illuminates, an index past the lights is ignored
+    pub fn with_light_link(mut self, light_index: usize, link: LightLink) -> Self {
+        if let Some(light_link) = self.light_links.get_mut(light_index) {
+            *light_link = link;
+        }
 
-        for light in &self.lights {
-            let pl = light.position(&hit.position) - hit.position;
-            let shadow_ray_direction = pl.normalize();
-            let shadow_ray = Ray::new(hit.position, shadow_ray_direction);
-
-            let mut occlusion_hit = None;
-            'occlusion_check: for primitive in &self.primitives
-            {
-                if let Some(hit) = primitive.hit(&shadow_ray, interval) {
-                    occlusion_hit = match hit.material.material_transparency() {
-                        MaterialTransparency::Opaque => Some(hit),
-                        MaterialTransparency::Transparent => Some(hit), // For now (until beer's law can be simulated) have transparent objects also cast shadows
-                    };
-
-                    break 'occlusion_check;
-                }
-            }
+        self
+    }
+
+    /// Restrict which primitives the emissive primitive at `primitive_index` illuminates.
+    /// Ignored when the primitive is not an emitter
+    pub fn with_emitter_link(mut self, primitive_index: usize, link: LightLink) -> Self {
+        let index = self.area_lights.iter()
+            .position(|area_light| area_light.primitive_index() == primitive_index);
+
+        if let Some(index) = index {
+            self.area_light_links[index] = link;
+        }
+
+        self
+    }
 
-            if occlusion_hit.is_none() {
-                combined_light += light.color(shadow_ray.direction(), &hit.normal, pl.magnitude_squared());
+    /// Leave emissive primitives to be found by bounces instead of sampling them as area lights
+    pub fn without_area_lights(mut self) -> Self {
+        self.area_lights.clear();
+        self.area_light_links.clear();
+        self.index_lights();
+        self
+    }
+
+    pub fn with_area_light_sampling(mut self, sampling: AreaLightSampling) -> Self {
+        self.area_light_sampling = sampling;
+        self
+    }
+
+    /// Light the scene with an HDR map, replacing the sky gradient wherever the map is visible
+    pub fn with_environment(mut self, environment: EnvironmentLight) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    pub fn area_lights(&self) -> &[AreaLight] {
+        &self.area_lights
+    }
+
+    /// Whether the emission of the primitive at `primitive_index` is sampled directly as an area light
+    pub fn is_area_light(&self, primitive_index: usize) -> bool {
+        self.area_lights.iter().any(|area_light| area_light.primitive_index() == primitive_index && self.samples_area_light(area_light))
+    }
+
+    /// Direct light stands in for diffuse bounces, so emitters hidden from diffuse rays are not sampled either
+    fn samples_area_light(&self, area_light: &AreaLight) -> bool {
+        self.visibility[area_light.primitive_index()].is_visible_to(RayKind::Diffuse)
+    }
+
+    /// Whether an environment map lights the scene, it is then sampled directly like the other lights
+    pub fn has_environment(&self) -> bool {
+        self.environment.is_some()
+    }
+
+    pub fn primitive_count(&self) -> usize {
+        self.primitives.len()
+    }
+
+    pub fn get_sky_color(&self, ray: &Ray, camera_ray: bool) -> Vec3 {
+        if let Some(environment) = &self.environment {
+            if !camera_ray || environment.is_visible_to_camera() {
+                return environment.radiance(ray.direction())
             }
         }
 
-        combined_light
+        let a = 0.5 * (ray.direction().y + 1.0);
+
+        (1.0 - a) * self.sky_attenuation.light_color + a * self.sky_attenuation.sky_color
     }
-}
 
-impl Hittable for Scene {
-    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit> {
-        let mut closest_hit: Option<RayHit> = None;
+    /// Closest hit among the primitives visible to rays of `kind`, along with the index of the primitive hit
+    pub fn trace(&self, ray: &Ray, interval: &Interval, kind: RayKind, statistics: &mut RenderStatistics) -> Option<(usize, RayHit<'_>)> {
+        let mut closest_hit: Option<(usize, RayHit)> = None;
+
+        for (index, primitive) in self.primitives.iter().enumerate() {
+            if !self.visibility[index].is_visible_to(kind) {
+                continue;
+            }
 
-        for primitive in &self.primitives {
             let closest_depth = match &closest_hit {
-                Some(hit) => hit.depth,
+                Some((_, hit)) => hit.depth,
                 None => interval.max(),
             };
 
+            statistics.intersection_tests += 1;
+
+            // The interval shrinks to the closest hit so far, so any hit found is closer
             if let Some(hit) = primitive.hit(ray, &Interval::new(interval.min(), closest_depth)) {
-                closest_hit = match closest_hit {
-                    Some(closest) => {
-                        if hit.depth < closest.depth {
-                            Some(hit)
-                        }
-                        else {
-                            Some(closest)
-                        }
-                    },
-                    None => Some(hit),
-                };
+                closest_hit = Some((index, hit));
             }
         }
 
         closest_hit
     }
+
+    /// Direct light arriving at `hit` on the primitive at `primitive_index`
+    pub fn shadow_ray(&self, hit: &RayHit, primitive_index: usize, interval: &Interval, statistics: &mut RenderStatistics) -> Vec3 {
+        let mut combined_light = Vec3::zeros();
+
+        for &index in &self.infinite_lights {
+            if !self.light_links[index].illuminates(primitive_index) {
+                continue;
+            }
+
+            let light = &self.lights[index];
+            let sample = light.sample(&hit.position);
+
+            if !self.is_occluded(hit, &sample, interval, statistics) {
+                combined_light += light.color(&sample, &hit.normal);
+            }
+        }
+
+        if let Some(environment) = &self.environment {
+            let sample = environment.sample(&hit.position);
+
+            if !self.is_occluded(hit, &sample, interval, statistics) {
+                combined_light += environment.color(&sample, &hit.normal);
+            }
+        }
+
+        // Picked lights are weighted by their probability so the estimate matches sampling every light
+        let mut rng = thread_rng();
+        match self.light_selection {
+            LightSelection::All => {
+                for local_light in &self.local_lights {
+                    combined_light += self.direct_light(*local_light, hit, primitive_index, interval, statistics);
+                }
+            },
+            LightSelection::Power { samples } => {
+                if let Some(distribution) = &self.power_distribution {
+                    for _ in 0..samples {
+                        let (index, pmf, _) = distribution.sample_discrete(rng.gen_range(0.0..1.0));
+                        combined_light += self.direct_light(self.local_lights[index], hit, primitive_index, interval, statistics) / (pmf * samples as f32);
+                    }
+                }
+            },
+            LightSelection::Bvh { samples } => {
+                for _ in 0..samples {
+                    if let Some((index, pmf)) = self.light_bvh.sample(&hit.position, rng.gen_range(0.0..1.0)) {
+                        combined_light += self.direct_light(self.local_lights[index], hit, primitive_index, interval, statistics) / (pmf * samples as f32);
+                    }
+                }
+            },
+        }
+
+        combined_light
+    }
+
+    fn direct_light(&self, local_light: LocalLight, hit: &RayHit, primitive_index: usize, interval: &Interval, statistics: &mut RenderStatistics) -> Vec3 {
+        match local_light {
+            LocalLight::Light(index) if self.light_links[index].illuminates(primitive_index) => {
+                let light = &self.lights[index];
+                let sample = light.sample(&hit.position);
+
+                if !self.is_occluded(hit, &sample, interval, statistics) {
+                    return light.color(&sample, &hit.normal)
+                }
+            },
+            LocalLight::Area(index) if self.area_light_links[index].illuminates(primitive_index) => {
+                let area_light = &self.area_lights[index];
+                let primitive = self.primitives[area_light.primitive_index()].as_ref();
+
+                if let Some((sample, irradiance)) = area_light.sample(primitive, &hit.position, &hit.normal, hit.time, self.area_light_sampling) {
+                    if !self.is_occluded(hit, &sample, interval, statistics) {
+                        return irradiance
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        Vec3::zeros()
+    }
+
+    fn is_occluded(&self, hit: &RayHit, sample: &LightSample, interval: &Interval, statistics: &mut RenderStatistics) -> bool {
+        let shadow_ray = Ray::with_time(hit.position, sample.direction, hit.time);
+        statistics.shadow_rays += 1;
+
+        // Only geometry between the point and the light occludes it, stopping short of the light's own surface.
+        // Lights at infinity are unbounded
+        let occlusion_interval = Interval::new(interval.min(), sample.distance - interval.min());
+
+        for (primitive, visibility) in self.primitives.iter().zip(&self.visibility) {
+            if !visibility.is_visible_to(RayKind::Shadow) {
+                continue;
+            }
+
+            statistics.intersection_tests += 1;
+            if let Some(hit) = primitive.hit(&shadow_ray, &occlusion_interval) {
+                match hit.material.material_transparency() {
+                    MaterialTransparency::Opaque => return true,
+                    MaterialTransparency::Transparent => return true, // For now (until beer's law can be simulated) have transparent objects also cast shadows
+                }
+            }
+        }
+
+        false
+    }
+}
+
+impl Hittable for Scene {
+    fn hit(&self, ray: &Ray, interval: &Interval) -> Option<RayHit<'_>> {
+        self.trace(ray, interval, RayKind::Camera, &mut RenderStatistics::default()).map(|(_, hit)| hit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::sphere::Sphere;
+    use crate::primitive::plane::{Plane, Rectangle};
+    use crate::material::diffuse::LambertianDiffuse;
+    use crate::material::emissive::Emissive;
+    use crate::light::LightPower;
+    use crate::light::radial_light::RadialLight;
+
+    fn test_scene() -> Scene {
+        let material = || Box::new(LambertianDiffuse::new(Vec3::new(0.5, 0.5, 0.5)));
+
+        // A ground plane with a sphere between it and a point light overhead
+        Scene::new(
+            SkyAttenuation { light_color: Vec3::zeros(), sky_color: Vec3::zeros() },
+            vec![
+                Box::new(Plane::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), material())),
+                Box::new(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 1.0, material())),
+            ],
+            vec![
+                Box::new(RadialLight::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 0.0, LightPower::Watts(100.0))),
+            ],
+        )
+    }
+
+    fn ground_light(scene: &Scene) -> f32 {
+        let interval = Interval::new(0.001, f32::INFINITY);
+        let ray = Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let (index, hit) = scene.trace(&ray, &interval, RayKind::Diffuse, &mut RenderStatistics::default()).unwrap();
+
+        scene.shadow_ray(&hit, index, &interval, &mut RenderStatistics::default()).x
+    }
+
+    #[test]
+    fn test_visibility_flags() {
+        let hidden = Visibility { camera: false, ..Visibility::default() };
+        let scene = test_scene().with_visibility(1, hidden);
+        let ray = Ray::new(Vec3::new(0.0, 2.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        // Hidden from the camera, but still seen by other rays and still casting a shadow
+        let statistics = &mut RenderStatistics::default();
+        assert!(scene.trace(&ray, &interval, RayKind::Camera, statistics).is_none());
+        assert_eq!(scene.trace(&ray, &interval, RayKind::Specular, statistics).unwrap().0, 1);
+        assert_eq!(statistics.intersection_tests, 3);
+        assert_eq!(ground_light(&scene), 0.0);
+
+        let shadowless = Visibility { shadow: false, ..Visibility::default() };
+        assert!(ground_light(&test_scene().with_visibility(1, shadowless)) > 0.0);
+    }
+
+    #[test]
+    fn test_light_linking() {
+        let shadowless = Visibility { shadow: false, ..Visibility::default() };
+
+        let excluded = test_scene().with_visibility(1, shadowless).with_light_link(0, LightLink::Exclude(vec![0]));
+        assert_eq!(ground_light(&excluded), 0.0);
+
+        let included = test_scene().with_visibility(1, shadowless).with_light_link(0, LightLink::Include(vec![0]));
+        assert!(ground_light(&included) > 0.0);
+    }
+
+    #[test]
+    fn test_emitter_hidden_from_diffuse_rays() {
+        // A floor under a rectangular emitter, with no other light
+        let scene = Scene::new(
+            SkyAttenuation { light_color: Vec3::zeros(), sky_color: Vec3::zeros() },
+            vec![
+                Box::new(Plane::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), Box::new(LambertianDiffuse::new(Vec3::new(0.5, 0.5, 0.5))))),
+                Box::new(Rectangle::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 1.0, 1.0, Box::new(Emissive::new(Vec3::new(1.0, 1.0, 1.0), 2.0)))),
+            ],
+            vec![],
+        );
+        assert!(scene.is_area_light(1));
+        assert!((0..100).map(|_| ground_light(&scene)).sum::<f32>() > 0.0);
+
+        // Diffuse bounces cannot see it, so neither can the direct light standing in for them
+        let hidden = scene.with_visibility(1, Visibility { diffuse: false, ..Visibility::default() });
+        assert!(!hidden.is_area_light(1));
+        assert_eq!((0..100).map(|_| ground_light(&hidden)).sum::<f32>(), 0.0);
+    }
+
+    #[test]
+    fn test_invalid_indices_are_ignored() {
+        let shadowless = Visibility { shadow: false, ..Visibility::default() };
+        let hidden = Visibility { camera: false, shadow: false, diffuse: false, specular: false, transmission: false };
+        let lit = ground_light(&test_scene().with_visibility(1, shadowless));
+
+        // Out of range primitives and lights, and a primitive that does not emit, leave the scene untouched
+        let scene = test_scene()
+            .with_visibility(1, shadowless)
+            .with_visibility(5, hidden)
+            .with_light_link(3, LightLink::Exclude(vec![0]))
+            .with_emitter_link(0, LightLink::Exclude(vec![0]));
+
+        assert_eq!(ground_light(&scene), lit);
+        assert!(lit > 0.0);
+    }
 }
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use crate::interval::Interval;
+use crate::output;
+
+/// Range of numbered frames to render, frame `first_frame` starts at time 0
+#[derive(Debug, Clone, Copy)]
+pub struct FrameSequence {
+    first_frame: u32,
+    last_frame: u32,
+    frames_per_second: f32,
+    shutter_fraction: f32,  // Portion of the frame duration the shutter is open, 0.5 is a 180 degree shutter
+    animate_objects: bool,  // Evaluate animated primitives at the frame time instead of time 0
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub number: u32,
+    pub time: f32,
+    pub shutter: Interval,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceError {
+    InvalidFrameRate,
+    InvalidFrameRange,
+    InvalidShutterFraction,
+}
+
+impl std::fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SequenceError::InvalidFrameRate => write!(f, "frames per second must be positive and finite"),
+            SequenceError::InvalidFrameRange => write!(f, "last frame comes before the first frame"),
+            SequenceError::InvalidShutterFraction => write!(f, "shutter fraction must be between 0 and 1"),
+        }
+    }
+}
+
+impl std::error::Error for SequenceError {}
+
+impl FrameSequence {
+    /// Frames `first_frame` to `last_frame` inclusive, with the shutter closed and objects animated
+    pub fn new(first_frame: u32, last_frame: u32, frames_per_second: f32) -> Result<Self, SequenceError> {
+        if !(frames_per_second > 0.0 && frames_per_second.is_finite()) {
+            return Err(SequenceError::InvalidFrameRate)
+        }
+
+        if last_frame < first_frame {
+            return Err(SequenceError::InvalidFrameRange)
+        }
+
+        Ok(FrameSequence {
+            first_frame,
+            last_frame,
+            frames_per_second,
+            shutter_fraction: 0.0,
+            animate_objects: true,
+        })
+    }
+
+    /// Keep the shutter open for `shutter_fraction` of each frame, 0.5 is a 180 degree shutter
+    pub fn with_shutter_fraction(mut self, shutter_fraction: f32) -> Result<Self, SequenceError> {
+        if !(0.0..=1.0).contains(&shutter_fraction) {
+            return Err(SequenceError::InvalidShutterFraction)
+        }
+
+        self.shutter_fraction = shutter_fraction;
+        Ok(self)
+    }
+
+    /// Whether animated primitives move with the frame time, or stay at time 0
+    pub fn with_object_animation(mut self, animate_objects: bool) -> Self {
+        self.animate_objects = animate_objects;
+        self
+    }
+
+    pub fn first_frame(&self) -> u32 {
+        self.first_frame
+    }
+
+    pub fn last_frame(&self) -> u32 {
+        self.last_frame
+    }
+
+    pub fn frames_per_second(&self) -> f32 {
+        self.frames_per_second
+    }
+
+    pub fn frame(&self, number: u32) -> Frame {
+        let time = number.saturating_sub(self.first_frame) as f32 / self.frames_per_second;
+
+        let shutter = if self.animate_objects {
+            Interval::new(time, time + self.shutter_fraction / self.frames_per_second)
+        }
+        else {
+            Interval::new(0.0, 0.0)
+        };
+
+        Frame { number, time, shutter }
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = Frame> + '_ {
+        (self.first_frame..=self.last_frame).map(|number| self.frame(number))
+    }
+
+    /// Numbered output path, `renders/frame.png` becomes `renders/frame_0001.png` for frame 1
+    pub fn frame_path(&self, path: &Path, number: u32) -> PathBuf {
+        output::path_with_suffix(path, &format!("{:04}", number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let sequence = FrameSequence::new(1, 25, 24.0).unwrap().with_shutter_fraction(0.5).unwrap();
+        let frames: Vec<Frame> = sequence.frames().collect();
+
+        assert_eq!(frames.len(), 25);
+        assert_eq!(frames[0].time, 0.0);
+        assert!(f32::abs(frames[24].time - 1.0) < 1e-6);
+        assert!(f32::abs(frames[24].shutter.max() - (1.0 + 0.5 / 24.0)) < 1e-6);
+    }
+
+    #[test]
+    fn test_frame_path() {
+        let sequence = FrameSequence::new(1, 10, 24.0).unwrap();
+
+        assert_eq!(sequence.frame_path(Path::new("renders/frame.png"), 7), PathBuf::from("renders/frame_0007.png"));
+    }
+
+    #[test]
+    fn test_reject_invalid_frame_rate() {
+        assert_eq!(FrameSequence::new(1, 10, 0.0).unwrap_err(), SequenceError::InvalidFrameRate);
+        assert_eq!(FrameSequence::new(1, 10, -24.0).unwrap_err(), SequenceError::InvalidFrameRate);
+        assert_eq!(FrameSequence::new(1, 10, f32::NAN).unwrap_err(), SequenceError::InvalidFrameRate);
+    }
+
+    #[test]
+    fn test_reject_invalid_range_and_shutter() {
+        assert_eq!(FrameSequence::new(10, 1, 24.0).unwrap_err(), SequenceError::InvalidFrameRange);
+        assert_eq!(FrameSequence::new(5, 5, 24.0).unwrap().frames().count(), 1);
+
+        let sequence = FrameSequence::new(1, 10, 24.0).unwrap();
+        assert_eq!(sequence.with_shutter_fraction(1.5).unwrap_err(), SequenceError::InvalidShutterFraction);
+        assert_eq!(sequence.with_shutter_fraction(-0.5).unwrap_err(), SequenceError::InvalidShutterFraction);
+        assert_eq!(sequence.with_shutter_fraction(f32::NAN).unwrap_err(), SequenceError::InvalidShutterFraction);
+
+        // Without object animation the shutter stays at time 0
+        let still = sequence.with_shutter_fraction(0.5).unwrap().with_object_animation(false);
+        assert_eq!(still.frame(5).shutter.max(), 0.0);
+    }
+}
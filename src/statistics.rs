@@ -0,0 +1,89 @@
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderStatistics {
+    pub primary_rays: u64,
+    pub secondary_rays: u64,
+    pub shadow_rays: u64,
+    pub intersection_tests: u64,
+    pub path_vertices: u64,
+    pub intersection_time: Duration,    // Scene traversal and primitive tests, summed over all threads
+    pub shading_time: Duration,         // Everything else done while rendering tiles, summed over all threads
+    pub render_time: Duration,          // Wall clock
+}
+
+impl RenderStatistics {
+    pub fn merge(&mut self, other: &RenderStatistics) {
+        self.primary_rays += other.primary_rays;
+        self.secondary_rays += other.secondary_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.intersection_tests += other.intersection_tests;
+        self.path_vertices += other.path_vertices;
+        self.intersection_time += other.intersection_time;
+        self.shading_time += other.shading_time;
+    }
+
+    pub fn total_rays(&self) -> u64 {
+        self.primary_rays + self.secondary_rays + self.shadow_rays
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.render_time.as_secs_f64();
+        if seconds <= 0.0 {
+            return 0.0
+        }
+
+        self.total_rays() as f64 / seconds
+    }
+
+    pub fn average_path_length(&self) -> f64 {
+        if self.primary_rays == 0 {
+            return 0.0
+        }
+
+        self.path_vertices as f64 / self.primary_rays as f64
+    }
+}
+
+impl fmt::Display for RenderStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let thread_time = (self.intersection_time + self.shading_time).as_secs_f64();
+        let percentage = |duration: Duration| if thread_time > 0.0 { 100.0 * duration.as_secs_f64() / thread_time } else { 0.0 };
+
+        writeln!(f, "Render statistics:")?;
+        writeln!(f, "  Render time:         {:?}", self.render_time)?;
+        writeln!(f, "  Primary rays:        {}", self.primary_rays)?;
+        writeln!(f, "  Secondary rays:      {}", self.secondary_rays)?;
+        writeln!(f, "  Shadow rays:         {}", self.shadow_rays)?;
+        writeln!(f, "  Rays per second:     {:.0}", self.rays_per_second())?;
+        writeln!(f, "  Intersection tests:  {}", self.intersection_tests)?;
+        writeln!(f, "  Average path length: {:.2}", self.average_path_length())?;
+        writeln!(f, "  Intersection time:   {:?} ({:.1}%)", self.intersection_time, percentage(self.intersection_time))?;
+        write!(f, "  Shading time:        {:?} ({:.1}%)", self.shading_time, percentage(self.shading_time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut statistics = RenderStatistics {
+            primary_rays: 10,
+            path_vertices: 25,
+            ..Default::default()
+        };
+
+        statistics.merge(&RenderStatistics {
+            primary_rays: 10,
+            secondary_rays: 5,
+            path_vertices: 15,
+            ..Default::default()
+        });
+
+        assert_eq!(statistics.total_rays(), 25);
+        assert_eq!(statistics.average_path_length(), 2.0);
+    }
+}
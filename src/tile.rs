@@ -0,0 +1,213 @@
+use crate::resolution::Resolution;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TileOrder {
+    Scanline,
+    Spiral,     // Outward from the center of the image
+    Hilbert,
+}
+
+impl Tile {
+    pub fn from_resolution(resolution: &Resolution) -> Self {
+        Tile {
+            x: 0,
+            y: 0,
+            width: resolution.width(),
+            height: resolution.height(),
+        }
+    }
+
+    pub fn pixel_count(&self) -> u32 {
+        self.width * self.height
+    }
+
+    /// Whether the continuous film position lies inside the tile
+    pub fn contains(&self, film_x: f32, film_y: f32) -> bool {
+        film_x >= self.x as f32 && film_x < (self.x + self.width) as f32 && film_y >= self.y as f32 && film_y < (self.y + self.height) as f32
+    }
+
+    /// Overlap of both tiles, empty if they do not overlap
+    pub fn intersect(&self, other: &Tile) -> Tile {
+        let x = u32::max(self.x, other.x);
+        let y = u32::max(self.y, other.y);
+        let max_x = u32::max(u32::min(self.x + self.width, other.x + other.width), x);
+        let max_y = u32::max(u32::min(self.y + self.height, other.y + other.height), y);
+
+        Tile {
+            x,
+            y,
+            width: max_x - x,
+            height: max_y - y,
+        }
+    }
+}
+
+/// Split a region of the image into tiles of at most `tile_size` x `tile_size` pixels, in render order
+pub fn generate_tiles(region: &Tile, tile_size: u32, order: TileOrder) -> Vec<Tile> {
+    let tile_size = u32::max(tile_size, 1);
+    let (width, height) = (region.width, region.height);
+    let tiles_x = width.div_ceil(tile_size);
+    let tiles_y = height.div_ceil(tile_size);
+
+    let grid_order = match order {
+        TileOrder::Scanline => scanline_order(tiles_x, tiles_y),
+        TileOrder::Spiral => spiral_order(tiles_x, tiles_y),
+        TileOrder::Hilbert => hilbert_order(tiles_x, tiles_y),
+    };
+
+    grid_order.into_iter()
+        .map(|(tile_x, tile_y)| {
+            let x = tile_x * tile_size;
+            let y = tile_y * tile_size;
+
+            Tile {
+                x: region.x + x,
+                y: region.y + y,
+                width: u32::min(tile_size, width - x),
+                height: u32::min(tile_size, height - y),
+            }
+        })
+        .collect()
+}
+
+fn scanline_order(tiles_x: u32, tiles_y: u32) -> Vec<(u32, u32)> {
+    (0..tiles_y)
+        .flat_map(|y| (0..tiles_x).map(move |x| (x, y)))
+        .collect()
+}
+
+fn spiral_order(tiles_x: u32, tiles_y: u32) -> Vec<(u32, u32)> {
+    let tile_count = (tiles_x * tiles_y) as usize;
+    let mut order = Vec::with_capacity(tile_count);
+
+    // Walk a square spiral around the center tile, skipping positions outside the grid
+    let (mut x, mut y) = (((tiles_x as i64) - 1) / 2, ((tiles_y as i64) - 1) / 2);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut step_length = 1;
+    let mut direction = 0;
+
+    while order.len() < tile_count {
+        // Each step length is walked twice before growing
+        for _ in 0..2 {
+            let (dx, dy) = directions[direction];
+
+            for _ in 0..step_length {
+                if (0..tiles_x as i64).contains(&x) && (0..tiles_y as i64).contains(&y) {
+                    order.push((x as u32, y as u32));
+                }
+
+                x += dx;
+                y += dy;
+            }
+
+            direction = (direction + 1) % directions.len();
+        }
+
+        step_length += 1;
+    }
+
+    order
+}
+
+fn hilbert_order(tiles_x: u32, tiles_y: u32) -> Vec<(u32, u32)> {
+    let curve_size = u32::max(tiles_x, tiles_y).next_power_of_two();
+
+    let mut order = scanline_order(tiles_x, tiles_y);
+    order.sort_by_key(|&(x, y)| hilbert_index(curve_size, x, y));
+
+    order
+}
+
+fn hilbert_index(curve_size: u32, x: u32, y: u32) -> u64 {
+    let (mut x, mut y) = (x, y);
+    let mut index = 0;
+    let mut s = curve_size / 2;
+
+    while s > 0 {
+        let rx = u32::from(x & s > 0);
+        let ry = u32::from(y & s > 0);
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+
+        // Rotate the quadrant so the sub-curve is oriented correctly
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        x &= s - 1;
+        y &= s - 1;
+        s /= 2;
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_covers_image(resolution: Resolution, tiles: &[Tile]) {
+        let mut covered = vec![0; (resolution.width() * resolution.height()) as usize];
+
+        for tile in tiles {
+            for y in tile.y..(tile.y + tile.height) {
+                for x in tile.x..(tile.x + tile.width) {
+                    covered[(y * resolution.width() + x) as usize] += 1;
+                }
+            }
+        }
+
+        assert!(covered.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn test_tiles_cover_image() {
+        let resolution = Resolution::new(100, 37);
+
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let tiles = generate_tiles(&Tile::from_resolution(&resolution), 16, order);
+
+            assert_eq!(tiles.len(), 7 * 3);
+            assert_covers_image(resolution, &tiles);
+        }
+    }
+
+    #[test]
+    fn test_tiles_in_region() {
+        let region = Tile { x: 10, y: 5, width: 20, height: 12 };
+        let tiles = generate_tiles(&region, 8, TileOrder::Scanline);
+
+        assert_eq!(tiles.len(), 3 * 2);
+        assert_eq!(tiles[0], Tile { x: 10, y: 5, width: 8, height: 8 });
+        assert_eq!(tiles[5], Tile { x: 26, y: 13, width: 4, height: 4 });
+    }
+
+    #[test]
+    fn test_spiral_starts_in_center() {
+        let tiles = generate_tiles(&Tile::from_resolution(&Resolution::new(48, 48)), 16, TileOrder::Spiral);
+
+        assert_eq!(tiles[0], Tile { x: 16, y: 16, width: 16, height: 16 });
+    }
+
+    #[test]
+    fn test_hilbert_is_continuous() {
+        let tiles = generate_tiles(&Tile::from_resolution(&Resolution::new(64, 64)), 8, TileOrder::Hilbert);
+
+        for pair in tiles.windows(2) {
+            let distance = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(distance, 8);
+        }
+    }
+}
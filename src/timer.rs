@@ -5,6 +5,12 @@ pub struct Timer {
     delta_time: Duration,
 }
 
+impl Default for Timer {
+    fn default() -> Self {
+        Timer::new()
+    }
+}
+
 impl Timer {
     pub fn new() -> Self {
         Timer {
@@ -0,0 +1,232 @@
+use nalgebra_glm::{Vec3, Mat3};
+
+use crate::interval::Interval;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ToneMapOperator {
+    Clamp,
+    Reinhard,
+    ExtendedReinhard { white_point: f32 },
+    Hable,
+    AcesFilmic,
+    AgX,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TransferFunction {
+    Linear,
+    Gamma(f32),
+    Srgb,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMapping {
+    pub exposure: f32,  // Exposure compensation in EV (stops)
+    pub operator: ToneMapOperator,
+    pub transfer_function: TransferFunction,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            exposure: 0.0,
+            operator: ToneMapOperator::Clamp,
+            transfer_function: TransferFunction::Srgb,
+        }
+    }
+}
+
+impl ToneMapping {
+    /// Map a linear HDR color to a display encoded color in [0, 1]
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        let exposed = color * f32::powf(2.0, self.exposure);
+        let mapped = self.operator.map(exposed);
+
+        let display_range = Interval::new(0.0, 1.0);
+        mapped.map(|channel| self.transfer_function.encode(display_range.clamp(channel)))
+    }
+}
+
+impl ToneMapOperator {
+    pub fn map(&self, color: Vec3) -> Vec3 {
+        // NaN samples would otherwise poison the whole pixel, treat them as black
+        let color = color.map(|channel| if channel.is_nan() { 0.0 } else { f32::max(channel, 0.0) });
+
+        match *self {
+            ToneMapOperator::Clamp => color,
+            ToneMapOperator::Reinhard => Self::scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMapOperator::ExtendedReinhard { white_point } => {
+                let white_squared = white_point * white_point;
+                Self::scale_luminance(color, |l| l * (1.0 + l / white_squared) / (1.0 + l))
+            },
+            ToneMapOperator::Hable => Self::hable(color),
+            ToneMapOperator::AcesFilmic => Self::aces_filmic(color),
+            ToneMapOperator::AgX => Self::agx(color),
+        }
+    }
+
+    fn scale_luminance<F>(color: Vec3, curve: F) -> Vec3
+    where
+        F: Fn(f32) -> f32
+    {
+        let l = luminance(&color);
+        if l <= 0.0 {
+            return Vec3::zeros()
+        }
+
+        color * (curve(l) / l)
+    }
+
+    fn hable(color: Vec3) -> Vec3 {
+        // John Hable's Uncharted 2 filmic curve
+        fn curve(x: f32) -> f32 {
+            let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+            ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+        }
+
+        let exposure_bias = 2.0;
+        let white_scale = 1.0 / curve(11.2);
+
+        color.map(|channel| curve(channel * exposure_bias) * white_scale)
+    }
+
+    fn aces_filmic(color: Vec3) -> Vec3 {
+        // Stephen Hill's fit of the ACES RRT + sRGB ODT
+        let input = Mat3::new(
+            0.59719, 0.35458, 0.04823,
+            0.07600, 0.90834, 0.01566,
+            0.02840, 0.13383, 0.83777,
+        );
+        let output = Mat3::new(
+            1.60475, -0.53108, -0.07367,
+            -0.10208, 1.10813, -0.00605,
+            -0.00327, -0.07276, 1.07602,
+        );
+
+        let v = input * color;
+        let v = v.map(|x| (x * (x + 0.0245786) - 0.000090537) / (x * (0.983729 * x + 0.432951) + 0.238081));
+
+        output * v
+    }
+
+    fn agx(color: Vec3) -> Vec3 {
+        // Troy Sobotka's AgX base look, using the polynomial sigmoid approximation
+        let min_ev = -12.47393;
+        let max_ev = 4.026069;
+
+        let inset = Mat3::new(
+            0.8424791, 0.0784336, 0.07922375,
+            0.04232824, 0.8784686, 0.07916613,
+            0.04237565, 0.0784336, 0.879143,
+        );
+        let outset = Mat3::new(
+            1.196879, -0.09802088, -0.09902974,
+            -0.05289685, 1.151903, -0.09896118,
+            -0.05297164, -0.09804345, 1.151074,
+        );
+
+        let v = inset * color;
+        let v = v.map(|x| {
+            let log = f32::clamp(f32::log2(f32::max(x, 1e-10)), min_ev, max_ev);
+            let x = (log - min_ev) / (max_ev - min_ev);
+
+            let x2 = x * x;
+            let x4 = x2 * x2;
+            15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+        });
+
+        // The sigmoid output is display encoded, return to linear so the transfer function applies uniformly
+        let v = outset * v;
+        v.map(|x| f32::powf(f32::max(x, 0.0), 2.2))
+    }
+}
+
+impl TransferFunction {
+    pub fn encode(&self, value: f32) -> f32 {
+        match *self {
+            TransferFunction::Linear => value,
+            TransferFunction::Gamma(gamma) => f32::powf(value, 1.0 / gamma),
+            TransferFunction::Srgb => {
+                if value <= 0.0031308 {
+                    12.92 * value
+                }
+                else {
+                    1.055 * f32::powf(value, 1.0 / 2.4) - 0.055
+                }
+            },
+        }
+    }
+}
+
+/// Relative luminance of a linear Rec. 709 color
+pub fn luminance(color: &Vec3) -> f32 {
+    color.dot(&Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: [ToneMapOperator; 6] = [
+        ToneMapOperator::Clamp,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::ExtendedReinhard { white_point: 4.0 },
+        ToneMapOperator::Hable,
+        ToneMapOperator::AcesFilmic,
+        ToneMapOperator::AgX,
+    ];
+
+    #[test]
+    fn test_output_in_display_range() {
+        for operator in OPERATORS {
+            let tone_mapping = ToneMapping { operator, ..Default::default() };
+
+            for intensity in [0.0, 0.01, 0.5, 1.0, 10.0, 1000.0] {
+                let color = tone_mapping.apply(Vec3::new(intensity, intensity * 0.5, intensity * 0.1));
+                assert!(color.iter().all(|c| (0.0..=1.0).contains(c)), "{:?} out of range: {:?}", operator, color);
+            }
+        }
+    }
+
+    #[test]
+    fn test_monotonic() {
+        for operator in OPERATORS {
+            let mut previous = 0.0;
+
+            for step in 1..100 {
+                let intensity = step as f32 * 0.25;
+                let mapped = operator.map(Vec3::new(intensity, intensity, intensity)).x;
+                assert!(mapped >= previous, "{:?} is not monotonic at {}", operator, intensity);
+                previous = mapped;
+            }
+        }
+    }
+
+    #[test]
+    fn test_extended_reinhard_white_point() {
+        let operator = ToneMapOperator::ExtendedReinhard { white_point: 4.0 };
+        let mapped = operator.map(Vec3::new(4.0, 4.0, 4.0));
+
+        assert!(f32::abs(mapped.x - 1.0) < 1e-4);
+    }
+
+    #[test]
+    fn test_exposure() {
+        let tone_mapping = ToneMapping {
+            exposure: 1.0,
+            operator: ToneMapOperator::Clamp,
+            transfer_function: TransferFunction::Linear,
+        };
+
+        assert_eq!(tone_mapping.apply(Vec3::new(0.25, 0.25, 0.25)), Vec3::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_srgb_transfer() {
+        let srgb = TransferFunction::Srgb;
+
+        assert_eq!(srgb.encode(0.0), 0.0);
+        assert!(f32::abs(srgb.encode(1.0) - 1.0) < 1e-6);
+        assert!(f32::abs(srgb.encode(0.18) - 0.4613) < 1e-3);
+    }
+}